@@ -0,0 +1,43 @@
+use std::{hint::black_box, time::Instant};
+
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use rusty_snake_env::{EnvConfig, SnakeEnv, VecEnv, ACTIONS};
+
+const STEPS: usize = 200_000;
+const ENVS: usize = 64;
+
+// Random actions, so episodes end often and resets are part of the measure
+fn main() {
+    let mut actions = StdRng::seed_from_u64(0);
+
+    let mut env = SnakeEnv::new(EnvConfig::default());
+    env.reset(0);
+    let start = Instant::now();
+    for seed in 0..STEPS as u64 {
+        let step = env.step(ACTIONS[actions.gen_range(0..ACTIONS.len())]);
+        if step.done {
+            env.reset(seed);
+        }
+        black_box(step);
+    }
+    report("SnakeEnv", STEPS, start);
+
+    let mut envs = VecEnv::new(ENVS, EnvConfig::default());
+    envs.reset(0);
+    let start = Instant::now();
+    for _ in 0..STEPS / ENVS {
+        let actions = (0..ENVS)
+            .map(|_| ACTIONS[actions.gen_range(0..ACTIONS.len())])
+            .collect::<Vec<_>>();
+        black_box(envs.step(&actions));
+    }
+    report(&format!("VecEnv of {ENVS}"), STEPS / ENVS * ENVS, start);
+}
+
+fn report(name: &str, steps: usize, start: Instant) {
+    let seconds = start.elapsed().as_secs_f64();
+    println!(
+        "{name}: {steps} steps in {seconds:.2}s, {:.0} steps/s",
+        steps as f64 / seconds
+    );
+}
@@ -0,0 +1,295 @@
+//! The snake rules as a reinforcement learning environment, in the style of Gymnasium.
+//!
+//! `SnakeEnv` plays a single snake on the same `SnakeWorld` the game and the simulator use,
+//! `VecEnv` steps many of them at once across threads. Nothing is rendered.
+
+mod observation;
+mod vec_env;
+
+use rand::{rngs::StdRng, SeedableRng};
+use rusty_snake::sim::{
+    board::{Arena, Direction, Food, GameMode},
+    level::Level,
+    world::{SnakeWorld, StepOutcome},
+};
+
+pub use observation::{Observation, CHANNELS, FEATURES};
+pub use vec_env::VecEnv;
+
+/// The actions, in the order of their index.
+pub const ACTIONS: [Direction; 4] = Direction::ALL;
+
+/// Reward for each thing that can happen in a step, summed when several do.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Reward {
+    /// Eating grow food.
+    pub food: f32,
+    /// Eating shrink food.
+    pub shrink_food: f32,
+    /// Every step the snake survives.
+    pub survival: f32,
+    /// Dying, usually negative.
+    pub death: f32,
+    /// Filling the whole board.
+    pub win: f32,
+}
+
+impl Default for Reward {
+    fn default() -> Self {
+        Self {
+            food: 1.,
+            shrink_food: 0.,
+            survival: 0.,
+            death: -1.,
+            win: 10.,
+        }
+    }
+}
+
+/// Board and rules of every episode.
+#[derive(Clone, Debug)]
+pub struct EnvConfig {
+    pub level: Level,
+    pub mode: GameMode,
+    pub reward: Reward,
+    /// Steps after which an episode is cut short, so a snake going around in circles ends.
+    pub max_steps: u32,
+}
+
+impl Default for EnvConfig {
+    fn default() -> Self {
+        Self {
+            level: Level::open(Arena::default()),
+            mode: GameMode::default(),
+            reward: Reward::default(),
+            max_steps: 1000,
+        }
+    }
+}
+
+/// What a step led to, `done` when the episode is over and needs a reset.
+#[derive(Clone, Debug)]
+pub struct Step {
+    pub observation: Observation,
+    pub reward: f32,
+    pub done: bool,
+    /// Whether it ended because of `max_steps` rather than death or a win.
+    pub truncated: bool,
+}
+
+/// One snake alone on the board, driven one step at a time.
+#[derive(Clone, Debug)]
+pub struct SnakeEnv {
+    config: EnvConfig,
+    world: SnakeWorld,
+    rng: StdRng,
+}
+
+impl SnakeEnv {
+    pub fn new(config: EnvConfig) -> Self {
+        let world = SnakeWorld::from_level(&config.level).with_mode(config.mode);
+        Self {
+            config,
+            world,
+            rng: StdRng::seed_from_u64(0),
+        }
+    }
+
+    pub fn config(&self) -> &EnvConfig {
+        &self.config
+    }
+
+    /// The game underneath, to read anything the observation leaves out.
+    pub fn world(&self) -> &SnakeWorld {
+        &self.world
+    }
+
+    /// Starts a new episode, the same seed and actions always play the same episode.
+    pub fn reset(&mut self, seed: u64) -> Observation {
+        self.world = SnakeWorld::from_level(&self.config.level).with_mode(self.config.mode);
+        self.rng = StdRng::seed_from_u64(seed);
+        self.observe()
+    }
+
+    /// Moves the snake, turning back on itself is ignored like in the game.
+    ///
+    /// Stepping a finished episode changes nothing and gives no reward.
+    pub fn step(&mut self, action: Direction) -> Step {
+        if self.is_done() {
+            return Step {
+                observation: self.observe(),
+                reward: 0.,
+                done: true,
+                truncated: self.world.tick() >= self.config.max_steps,
+            };
+        }
+
+        let reward = self.config.reward;
+        let reward = match self.world.step(action, &mut self.rng) {
+            StepOutcome::Moved => reward.survival,
+            StepOutcome::Ate(Food::Grow) => reward.survival + reward.food,
+            StepOutcome::Ate(Food::Shrink) => reward.survival + reward.shrink_food,
+            StepOutcome::Died(_) => reward.death,
+            // The last food filled the board
+            StepOutcome::Won => reward.survival + reward.food + reward.win,
+        };
+        let truncated = !self.world.is_over() && self.world.tick() >= self.config.max_steps;
+        Step {
+            observation: self.observe(),
+            reward,
+            done: self.world.is_over() || truncated,
+            truncated,
+        }
+    }
+
+    /// Same as `step` with the action given by its index in `ACTIONS`.
+    ///
+    /// Panics when there is no action at that index, like a wrong action space would.
+    pub fn step_index(&mut self, action: usize) -> Step {
+        assert!(
+            action < ACTIONS.len(),
+            "no action {action}, there are {}",
+            ACTIONS.len()
+        );
+        self.step(ACTIONS[action])
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.world.is_over() || self.world.tick() >= self.config.max_steps
+    }
+
+    pub fn observe(&self) -> Observation {
+        Observation::new(&self.world)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // The snake at (1, 1) heading up, food waiting at (2, 2)
+    fn square() -> EnvConfig {
+        let level = Level::parse(
+            ".....\n\
+             .....\n\
+             ..*..\n\
+             .S...\n\
+             .....\n",
+        )
+        .unwrap();
+        EnvConfig {
+            level,
+            reward: Reward {
+                food: 2.,
+                shrink_food: -0.5,
+                survival: 0.25,
+                death: -3.,
+                win: 20.,
+            },
+            ..EnvConfig::default()
+        }
+    }
+
+    // Goes around a square of four cells, where a short snake never bites itself
+    fn circle(step: usize) -> Direction {
+        [
+            Direction::Up,
+            Direction::Right,
+            Direction::Down,
+            Direction::Left,
+        ][step % 4]
+    }
+
+    #[test]
+    fn the_same_seed_plays_the_same_episode() {
+        let config = EnvConfig {
+            max_steps: 200,
+            ..EnvConfig::default()
+        };
+        let mut env = SnakeEnv::new(config.clone());
+        let mut other = SnakeEnv::new(config);
+        let play = |env: &mut SnakeEnv| {
+            let mut observations = vec![env.reset(11)];
+            for step in 0..100 {
+                let step = env.step(circle(step));
+                observations.push(step.observation);
+            }
+            observations
+        };
+        let first = play(&mut env);
+        assert_eq!(play(&mut other), first);
+        // A reset forgets the episode before
+        assert_eq!(play(&mut env), first);
+    }
+
+    #[test]
+    fn each_outcome_gives_its_reward() {
+        let mut env = SnakeEnv::new(square());
+        env.reset(0);
+        let step = env.step(Direction::Up);
+        assert_eq!(step.reward, 0.25);
+        assert!(!step.done);
+        let step = env.step(Direction::Right);
+        assert_eq!(step.reward, 0.25 + 2.);
+        assert_eq!(env.world().segments().len(), 3);
+
+        // Straight into the wall on the right
+        let step = env.step(Direction::Right);
+        assert_eq!(step.reward, 0.25);
+        let step = env.step(Direction::Right);
+        assert_eq!(step.reward, 0.25);
+        let step = env.step(Direction::Right);
+        assert_eq!(step.reward, -3.);
+        assert!(step.done);
+        assert!(!step.truncated);
+        assert_eq!(step.observation.features[11], 0.);
+
+        // Nothing more happens until the next reset
+        let step = env.step(Direction::Up);
+        assert_eq!(step.reward, 0.);
+        assert!(step.done);
+    }
+
+    #[test]
+    fn winning_adds_the_last_food_to_the_win() {
+        // Room for the snake and one more segment
+        let level = Level::parse(
+            "#####\n\
+             #####\n\
+             #*###\n\
+             #S###\n\
+             #.###\n",
+        )
+        .unwrap();
+        let mut env = SnakeEnv::new(EnvConfig { level, ..square() });
+        env.reset(0);
+        let step = env.step(Direction::Up);
+        assert_eq!(step.reward, 0.25 + 2. + 20.);
+        assert!(step.done);
+        assert!(env.world().is_won());
+    }
+
+    #[test]
+    #[should_panic(expected = "no action 4")]
+    fn an_action_index_past_the_end_panics() {
+        let mut env = SnakeEnv::new(EnvConfig::default());
+        env.reset(0);
+        env.step_index(ACTIONS.len());
+    }
+
+    #[test]
+    fn long_episodes_are_truncated() {
+        let mut env = SnakeEnv::new(EnvConfig {
+            max_steps: 8,
+            ..square()
+        });
+        env.reset(0);
+        for step in 0..7 {
+            assert!(!env.step(circle(step)).done);
+        }
+        let step = env.step(circle(7));
+        assert!(step.done);
+        assert!(step.truncated);
+        assert!(!env.world().is_over());
+    }
+}
@@ -0,0 +1,98 @@
+use rusty_snake::sim::{
+    board::{Direction, Food, Position},
+    world::SnakeWorld,
+};
+
+use crate::ACTIONS;
+
+/// Planes of the grid: head, body, grow food, shrink food and obstacles.
+pub const CHANNELS: usize = 5;
+
+/// Length of the feature vector, see `Observation::features`.
+pub const FEATURES: usize = 12;
+
+/// What the agent sees of the board after a reset or a step.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Observation {
+    /// `CHANNELS` planes of `height` rows of `width` cells, 1 where the thing is and 0 elsewhere.
+    ///
+    /// Row `y` is the board row `y`, so `Up` goes to the next row.
+    pub grid: Vec<f32>,
+    pub width: usize,
+    pub height: usize,
+    /// For each action in order: whether the snake heads that way, then whether it dies going there.
+    /// Then the offset to the closest grow food divided by the board size, 0 without food,
+    /// then the length of the snake over the number of cells, then 1 while it is alive.
+    pub features: [f32; FEATURES],
+}
+
+impl Observation {
+    pub fn new(world: &SnakeWorld) -> Self {
+        let arena = world.arena();
+        let (width, height) = (arena.width as usize, arena.height as usize);
+        let mut grid = vec![0.; CHANNELS * width * height];
+        let mut set = |channel: usize, position: Position| {
+            if arena.contains(position) {
+                grid[(channel * height + position.y as usize) * width + position.x as usize] = 1.;
+            }
+        };
+
+        let snake = &world.snakes()[0];
+        // A dead head may be off the board or in a wall, then it is left out
+        set(0, snake.head());
+        for position in &snake.segments()[1..] {
+            set(1, *position);
+        }
+        for (position, food) in world.food() {
+            match food {
+                Food::Grow => set(2, *position),
+                Food::Shrink => set(3, *position),
+            }
+        }
+        for position in world.obstacles() {
+            set(4, *position);
+        }
+
+        let mut features = [0.; FEATURES];
+        for (index, direction) in ACTIONS.into_iter().enumerate() {
+            features[index] = (snake.direction() == direction) as u8 as f32;
+            features[4 + index] = is_deadly(world, direction) as u8 as f32;
+        }
+        let head = snake.head();
+        if let Some(food) = world
+            .food()
+            .iter()
+            .filter(|(_, food)| *food == Food::Grow)
+            .map(|(position, _)| *position)
+            .min_by_key(|food| food.x.abs_diff(head.x) + food.y.abs_diff(head.y))
+        {
+            features[8] = (food.x - head.x) as f32 / width as f32;
+            features[9] = (food.y - head.y) as f32 / height as f32;
+        }
+        features[10] = snake.segments().len() as f32 / (width * height) as f32;
+        features[11] = snake.death().is_none() as u8 as f32;
+
+        Self {
+            grid,
+            width,
+            height,
+            features,
+        }
+    }
+
+    /// Shape of the grid as channels, rows and columns.
+    pub fn shape(&self) -> [usize; 3] {
+        [CHANNELS, self.height, self.width]
+    }
+}
+
+// Turning back is ignored by the game, so it is as deadly as going straight
+fn is_deadly(world: &SnakeWorld, direction: Direction) -> bool {
+    let snake = &world.snakes()[0];
+    let direction = if direction == snake.direction().opposite() {
+        snake.direction()
+    } else {
+        direction
+    };
+    world.is_deadly(world.next_position(snake.head(), direction))
+}
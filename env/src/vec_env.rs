@@ -0,0 +1,116 @@
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use rayon::prelude::*;
+use rusty_snake::sim::board::Direction;
+
+use crate::{EnvConfig, Observation, SnakeEnv, Step};
+
+/// Many environments stepped together, spread over the rayon thread pool.
+///
+/// A finished episode starts again right away: its step holds the reward and `done` of the
+/// episode that ended, but already the observation of the next one.
+pub struct VecEnv {
+    envs: Vec<SnakeEnv>,
+    // Seeds of the episodes after the first ones
+    seeds: StdRng,
+}
+
+impl VecEnv {
+    pub fn new(count: usize, config: EnvConfig) -> Self {
+        Self {
+            envs: vec![SnakeEnv::new(config); count],
+            seeds: StdRng::seed_from_u64(0),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.envs.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.envs.is_empty()
+    }
+
+    pub fn envs(&self) -> &[SnakeEnv] {
+        &self.envs
+    }
+
+    /// Resets every environment, the first one with `seed`, the next ones with the following seeds.
+    pub fn reset(&mut self, seed: u64) -> Vec<Observation> {
+        self.seeds = StdRng::seed_from_u64(seed);
+        self.envs
+            .par_iter_mut()
+            .enumerate()
+            .map(|(index, env)| env.reset(seed.wrapping_add(index as u64)))
+            .collect()
+    }
+
+    /// Steps each environment with its action, `actions` holding one per environment.
+    pub fn step(&mut self, actions: &[Direction]) -> Vec<Step> {
+        assert_eq!(actions.len(), self.envs.len(), "one action per environment");
+        // Drawn up front so the episodes don't depend on how the threads are scheduled
+        let seeds = (0..self.envs.len())
+            .map(|_| self.seeds.gen())
+            .collect::<Vec<u64>>();
+        self.envs
+            .par_iter_mut()
+            .zip(actions)
+            .zip(seeds)
+            .map(|((env, action), seed)| {
+                let mut step = env.step(*action);
+                if step.done {
+                    step.observation = env.reset(seed);
+                }
+                step
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> EnvConfig {
+        EnvConfig {
+            max_steps: 4,
+            ..EnvConfig::default()
+        }
+    }
+
+    #[test]
+    fn finished_episodes_start_again() {
+        let mut envs = VecEnv::new(3, config());
+        envs.reset(5);
+        let actions = [Direction::Up, Direction::Right, Direction::Up];
+        for _ in 0..3 {
+            assert!(envs.step(&actions).iter().all(|step| !step.done));
+        }
+
+        let steps = envs.step(&actions);
+        for (step, env) in steps.iter().zip(envs.envs()) {
+            assert!(step.done);
+            assert!(step.truncated);
+            // The reward of the old episode, the observation of the new one
+            assert_eq!(step.reward, 0.);
+            assert_eq!(env.world().tick(), 0);
+            assert_eq!(step.observation, env.observe());
+        }
+        let steps = envs.step(&actions);
+        assert!(steps.iter().all(|step| !step.done));
+        assert!(envs.envs().iter().all(|env| env.world().tick() == 1));
+    }
+
+    #[test]
+    fn the_same_seed_plays_the_same_episodes() {
+        let play = || {
+            let mut envs = VecEnv::new(4, config());
+            let mut observations = envs.reset(3);
+            for step in 0..20 {
+                let actions = [Direction::ALL[step % 4]; 4];
+                observations.extend(envs.step(&actions).into_iter().map(|step| step.observation));
+            }
+            observations
+        };
+        assert_eq!(play(), play());
+    }
+}
@@ -0,0 +1,281 @@
+use std::{path::PathBuf, process::ExitCode, time::Duration};
+
+use bevy::{app::ScheduleRunnerPlugin, prelude::*};
+use rusty_snake::{
+    game::{game::GameRng, snake::Simulation},
+    sim::{
+        ai::{SnakeController, Strategy},
+        bot::{BotAddress, BotController, DEFAULT_TIMEOUT},
+        level::Level,
+        replay::Replay,
+        world::{DeathCause, SnakeWorld},
+    },
+    utils::{Arena, Direction, GameMode},
+};
+use serde::Serialize;
+
+const USAGE: &str =
+    "usage: rusty_snake-sim [--seed <u64>] [--ticks <count>] [--mode classic|wrap] [--arena <width>x<height>] [--level <name>] [--inputs <replay file> | --ai greedy|bfs|hamiltonian | --bot [tcp:]<host>:<port>|unix:<path> [--bot-timeout <ms>]]";
+
+/// Runs a game without window nor audio and prints how it ended as JSON.
+///
+/// Without an input file the snake keeps going in its current direction, unless a computer player or a bot drives it.
+/// A bot connects to the given address and answers a JSON line for every tick, see `BotController`.
+/// A versus replay brings the second snake along, the report is then about the first one.
+fn main() -> ExitCode {
+    let options = match Options::from_args() {
+        Ok(options) => options,
+        Err(err) => {
+            eprintln!("{err}\n{USAGE}");
+            return ExitCode::from(2);
+        }
+    };
+
+    if options.inputs.is_some() && options.ai.is_some() {
+        eprintln!("--ai can't be used with an input file\n{USAGE}");
+        return ExitCode::from(2);
+    }
+    if options.bot.is_some() && (options.inputs.is_some() || options.ai.is_some()) {
+        eprintln!("--bot can't be used with an input file nor --ai\n{USAGE}");
+        return ExitCode::from(2);
+    }
+    let script = match &options.inputs {
+        Some(path) => match Replay::load(path) {
+            Ok(replay) => replay,
+            Err(err) => {
+                eprintln!("could not read {}: {err}", path.display());
+                return ExitCode::FAILURE;
+            }
+        },
+        None => Replay::new(0, GameMode::default(), Arena::default()),
+    };
+    let seed = options
+        .seed
+        .or(options.inputs.as_ref().map(|_| script.seed))
+        .unwrap_or_else(rand::random);
+    let mode = options
+        .mode
+        .or(options.inputs.as_ref().map(|_| script.mode))
+        .unwrap_or_default();
+    let arena = options
+        .arena
+        .or(options.inputs.as_ref().map(|_| script.arena))
+        .unwrap_or_default();
+    // A level brings its own arena
+    let replay_level = script.level.is_some() || script.board.is_some();
+    if options.arena.is_some() && (options.level.is_some() || replay_level) {
+        eprintln!("--arena can't be used with a level\n{USAGE}");
+        return ExitCode::from(2);
+    }
+    let level = match &options.level {
+        Some(name) => {
+            Level::load_named(name).map_err(|err| format!("could not read level {name}: {err}"))
+        }
+        None if replay_level => script
+            .load_level()
+            .map_err(|err| format!("could not read the level of the replay: {err}")),
+        None => Ok(Level::open(arena)),
+    };
+    let level = match level {
+        Ok(level) => level,
+        Err(err) => {
+            eprintln!("{err}");
+            return ExitCode::FAILURE;
+        }
+    };
+    if let Some(strategy) = options.ai.filter(|ai| !ai.supports(&level, 1)) {
+        eprintln!(
+            "the {} player needs an open board with an even number of cells\n{USAGE}",
+            strategy.name()
+        );
+        return ExitCode::from(2);
+    }
+    let ticks = options.ticks.unwrap_or(match options.inputs {
+        Some(_) => script.len() as u32,
+        None => u32::MAX,
+    });
+
+    let mut world = SnakeWorld::from_level(&level).with_mode(mode);
+    if script.players() > 1 {
+        world = world.with_rival();
+    }
+
+    // The report goes to stdout, so the waiting message goes to stderr
+    let bot = match &options.bot {
+        Some(address) => {
+            eprintln!("waiting for a bot on {address}");
+            match BotController::listen(address, options.bot_timeout.unwrap_or(DEFAULT_TIMEOUT)) {
+                Ok(bot) => Some(bot),
+                Err(err) => {
+                    eprintln!("could not connect the bot: {err}");
+                    return ExitCode::FAILURE;
+                }
+            }
+        }
+        None => None,
+    };
+
+    App::new()
+        .add_plugins(MinimalPlugins.set(ScheduleRunnerPlugin::run_loop(Duration::ZERO)))
+        .insert_resource(Simulation(world))
+        .insert_resource(GameRng::new(seed))
+        .insert_resource(Script {
+            inputs: script.inputs,
+            ticks,
+            controller: options.ai.map(Strategy::controller),
+            bot,
+        })
+        .add_systems(Update, headless_step)
+        .run();
+
+    ExitCode::SUCCESS
+}
+
+struct Options {
+    seed: Option<u64>,
+    ticks: Option<u32>,
+    mode: Option<GameMode>,
+    arena: Option<Arena>,
+    level: Option<String>,
+    inputs: Option<PathBuf>,
+    ai: Option<Strategy>,
+    bot: Option<BotAddress>,
+    bot_timeout: Option<Duration>,
+}
+
+impl Options {
+    fn from_args() -> Result<Self, String> {
+        let mut options = Options {
+            seed: None,
+            ticks: None,
+            mode: None,
+            arena: None,
+            level: None,
+            inputs: None,
+            ai: None,
+            bot: None,
+            bot_timeout: None,
+        };
+
+        let mut args = std::env::args().skip(1);
+        while let Some(arg) = args.next() {
+            let value = args
+                .next()
+                .ok_or_else(|| format!("missing value for {arg}"))?;
+            match arg.as_str() {
+                "--seed" => options.seed = Some(value.parse().map_err(|_| "invalid seed")?),
+                "--ticks" => options.ticks = Some(value.parse().map_err(|_| "invalid ticks")?),
+                "--mode" => options.mode = Some(GameMode::from_name(&value).ok_or("invalid mode")?),
+                "--arena" => options.arena = Some(parse_arena(&value).ok_or("invalid arena")?),
+                "--level" => options.level = Some(value),
+                "--inputs" => options.inputs = Some(PathBuf::from(value)),
+                "--ai" => options.ai = Some(Strategy::from_name(&value).ok_or("invalid ai")?),
+                "--bot" => {
+                    options.bot = Some(BotAddress::parse(&value).ok_or("invalid bot address")?)
+                }
+                "--bot-timeout" => {
+                    let millis = value.parse().map_err(|_| "invalid bot timeout")?;
+                    options.bot_timeout = Some(Duration::from_millis(millis));
+                }
+                _ => return Err(format!("unknown argument {arg}")),
+            }
+        }
+
+        Ok(options)
+    }
+}
+
+fn parse_arena(value: &str) -> Option<Arena> {
+    let (width, height) = value.split_once('x')?;
+    let arena = Arena::new(width.parse().ok()?, height.parse().ok()?);
+    let sizes = Arena::MIN_SIZE..=Arena::MAX_SIZE;
+    (sizes.contains(&arena.width) && sizes.contains(&arena.height)).then_some(arena)
+}
+
+#[derive(Resource)]
+struct Script {
+    // One list per snake
+    inputs: Vec<Vec<Direction>>,
+    ticks: u32,
+    // Drives the first snake instead of the inputs
+    controller: Option<Box<dyn SnakeController>>,
+    // Drives the first snake over a socket, told when the game ends
+    bot: Option<BotController>,
+}
+
+#[derive(Serialize)]
+struct Report {
+    seed: u64,
+    mode: &'static str,
+    arena: String,
+    level: Option<String>,
+    ticks: u32,
+    score: u32,
+    length: usize,
+    won: bool,
+    death: Option<&'static str>,
+    players: usize,
+    // Index of the winning snake of a versus game
+    winner: Option<usize>,
+    // States the bot did not answer in time
+    bot_timeouts: Option<u32>,
+}
+
+fn headless_step(
+    mut simulation: ResMut<Simulation>,
+    mut rng: ResMut<GameRng>,
+    mut script: ResMut<Script>,
+    mut app_exit_events: EventWriter<AppExit>,
+) {
+    let tick = simulation.tick();
+    let bot_left = script
+        .bot
+        .as_ref()
+        .is_some_and(BotController::is_disconnected);
+    if tick < script.ticks && !simulation.is_over() && !bot_left {
+        let mut inputs = simulation
+            .snakes()
+            .iter()
+            .enumerate()
+            .map(|(player, snake)| {
+                script
+                    .inputs
+                    .get(player)
+                    .and_then(|inputs| inputs.get(tick as usize))
+                    .copied()
+                    .unwrap_or(snake.direction())
+            })
+            .collect::<Vec<_>>();
+        if let Some(controller) = &mut script.controller {
+            inputs[0] = controller.next_direction(&simulation, 0);
+        }
+        if let Some(bot) = &mut script.bot {
+            inputs[0] = bot.next_direction(&simulation, 0);
+            for err in bot.take_malformed_answers() {
+                eprintln!("ignoring a malformed answer from the bot: {err}");
+            }
+        }
+        simulation.step_all(&inputs, &mut **rng);
+        return;
+    }
+
+    let report = Report {
+        seed: rng.seed,
+        mode: simulation.mode().name(),
+        arena: format!("{}x{}", simulation.arena().width, simulation.arena().height),
+        level: simulation.level().map(str::to_string),
+        ticks: tick,
+        score: simulation.score(),
+        length: simulation.segments().len(),
+        won: simulation.is_won(),
+        death: simulation.death().map(DeathCause::name),
+        players: simulation.snakes().len(),
+        winner: simulation.winner(),
+        bot_timeouts: script.bot.as_ref().map(BotController::timeouts),
+    };
+    if let Some(bot) = &mut script.bot {
+        bot.finish(&simulation, 0);
+    }
+    println!("{}", serde_json::to_string(&report).unwrap());
+    app_exit_events.send(AppExit::Success);
+}
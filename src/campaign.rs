@@ -0,0 +1,124 @@
+use std::{collections::BTreeSet, fs, io, path::PathBuf};
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    game::{game::GameWonEvent, snake::Simulation},
+    sim::level::{levels_dir, Goal, Level},
+    storage::data_dir,
+};
+
+/// Levels of the campaign in the order they unlock, one name per line of `campaign.txt` in the levels folder.
+pub fn campaign_levels() -> Vec<String> {
+    let Ok(text) = fs::read_to_string(levels_dir().join("campaign.txt")) else {
+        return Vec::new();
+    };
+    text.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+/// Campaign levels completed so far, saved between runs.
+#[derive(Resource, Debug, Default, Serialize, Deserialize)]
+pub struct CampaignProgress {
+    completed: BTreeSet<String>,
+}
+
+fn progress_path() -> PathBuf {
+    data_dir().join("campaign.json")
+}
+
+impl CampaignProgress {
+    /// Reads the saved progress, starting a new campaign when there is none yet.
+    pub fn load() -> Self {
+        let Ok(text) = fs::read_to_string(progress_path()) else {
+            return Self::default();
+        };
+        serde_json::from_str(&text).unwrap_or_else(|err| {
+            warn!("Ignoring unreadable campaign progress: {err}");
+            Self::default()
+        })
+    }
+
+    pub fn save(&self) -> io::Result<()> {
+        fs::create_dir_all(data_dir())?;
+        fs::write(progress_path(), serde_json::to_string_pretty(self)?)
+    }
+
+    pub fn is_completed(&self, level: &str) -> bool {
+        self.completed.contains(level)
+    }
+
+    /// The first level is always open, the others once the one before is completed.
+    pub fn is_unlocked(&self, levels: &[String], index: usize) -> bool {
+        index == 0
+            || self.is_completed(&levels[index])
+            || levels
+                .get(index - 1)
+                .is_some_and(|previous| self.is_completed(previous))
+    }
+
+    pub fn completed_count(&self, levels: &[String]) -> usize {
+        levels
+            .iter()
+            .filter(|level| self.is_completed(level))
+            .count()
+    }
+}
+
+/// Present while playing a campaign level, the game then ends as soon as the goal is reached.
+#[derive(Resource, Debug)]
+pub struct CampaignRun {
+    levels: Vec<String>,
+    index: usize,
+    pub level: Level,
+    pub goal: Goal,
+}
+
+impl CampaignRun {
+    pub fn start(levels: Vec<String>, index: usize) -> io::Result<Self> {
+        let level = Level::load_named(&levels[index])?;
+        let goal = level.goal.ok_or_else(|| {
+            io::Error::new(io::ErrorKind::InvalidData, "campaign level without a goal")
+        })?;
+        Ok(Self {
+            levels,
+            index,
+            level,
+            goal,
+        })
+    }
+
+    pub fn has_next(&self) -> bool {
+        self.index + 1 < self.levels.len()
+    }
+
+    pub fn next(&self) -> io::Result<Self> {
+        Self::start(self.levels.clone(), self.index + 1)
+    }
+}
+
+/// Ends the game as a win once the goal of the level is reached, unlocking the next one.
+pub fn campaign_goal(
+    run: Option<Res<CampaignRun>>,
+    simulation: Res<Simulation>,
+    mut progress: ResMut<CampaignProgress>,
+    mut game_won_writer: EventWriter<GameWonEvent>,
+) {
+    let Some(run) = run else {
+        return;
+    };
+    if !simulation.is_changed() || simulation.is_over() || !run.goal.is_reached(&simulation) {
+        return;
+    }
+
+    game_won_writer.send(GameWonEvent);
+    if progress.completed.insert(run.level.name.clone()) {
+        if let Err(err) = progress.save() {
+            warn!("Could not save the campaign progress: {err}");
+        }
+    }
+}
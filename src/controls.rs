@@ -0,0 +1,301 @@
+use bevy::{
+    input::{
+        gamepad::{GamepadAxisType, GamepadButtonType},
+        keyboard::KeyboardInput,
+        InputSystem,
+    },
+    prelude::*,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::utils::Direction;
+
+/// Keys that can be bound to each action, on top of a gamepad button.
+pub const KEY_SLOTS: usize = 2;
+/// Key slot each player of a versus game steers with: WASD by default for the first one, the arrows for the second.
+pub const VERSUS_KEY_SLOTS: [usize; 2] = [1, 0];
+// How far the left stick must be pushed to count as a press
+const STICK_THRESHOLD: f32 = 0.5;
+
+pub fn controls_plugin(app: &mut App) {
+    app.init_resource::<ActionInput>()
+        .add_systems(PreUpdate, update_action_input.after(InputSystem));
+}
+
+/// What the player can do, whatever input it comes from.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Action {
+    Up,
+    Down,
+    Left,
+    Right,
+    Pause,
+    Confirm,
+    Back,
+}
+
+impl Action {
+    pub const ALL: [Action; 7] = [
+        Action::Up,
+        Action::Down,
+        Action::Left,
+        Action::Right,
+        Action::Pause,
+        Action::Confirm,
+        Action::Back,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Action::Up => "Up",
+            Action::Down => "Down",
+            Action::Left => "Left",
+            Action::Right => "Right",
+            Action::Pause => "Pause",
+            Action::Confirm => "Confirm",
+            Action::Back => "Back",
+        }
+    }
+
+    /// Direction the snake turns to on this action, if it is a turn.
+    pub fn direction(self) -> Option<Direction> {
+        match self {
+            Action::Up => Some(Direction::Up),
+            Action::Down => Some(Direction::Down),
+            Action::Left => Some(Direction::Left),
+            Action::Right => Some(Direction::Right),
+            Action::Pause | Action::Confirm | Action::Back => None,
+        }
+    }
+
+    // The left stick always steers, it is not rebindable
+    fn stick(self) -> Option<(GamepadAxisType, f32)> {
+        match self {
+            Action::Up => Some((GamepadAxisType::LeftStickY, 1.)),
+            Action::Down => Some((GamepadAxisType::LeftStickY, -1.)),
+            Action::Left => Some((GamepadAxisType::LeftStickX, -1.)),
+            Action::Right => Some((GamepadAxisType::LeftStickX, 1.)),
+            Action::Pause | Action::Confirm | Action::Back => None,
+        }
+    }
+}
+
+/// One of the inputs an action can be bound to.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BindingSlot {
+    Key(usize),
+    Button,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ActionBindings {
+    pub keys: [Option<KeyCode>; KEY_SLOTS],
+    pub button: Option<GamepadButtonType>,
+}
+
+impl ActionBindings {
+    const fn new(first: KeyCode, second: KeyCode, button: GamepadButtonType) -> Self {
+        Self {
+            keys: [Some(first), Some(second)],
+            button: Some(button),
+        }
+    }
+
+    /// Short label of what is bound to the slot, shown in the controls menu.
+    pub fn slot_name(&self, slot: BindingSlot) -> String {
+        let name = match slot {
+            BindingSlot::Key(index) => self.keys[index].map(|key| {
+                let name = format!("{key:?}");
+                name.strip_prefix("Key")
+                    .or(name.strip_prefix("Digit"))
+                    .unwrap_or(&name)
+                    .to_string()
+            }),
+            BindingSlot::Button => self.button.map(|button| format!("{button:?}")),
+        };
+        name.unwrap_or_else(|| "-".to_string())
+    }
+}
+
+/// Inputs bound to each action, edited from the controls menu.
+///
+/// One field per action, so a file missing one falls back to its default bindings.
+#[derive(Resource, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Controls {
+    up: ActionBindings,
+    down: ActionBindings,
+    left: ActionBindings,
+    right: ActionBindings,
+    pause: ActionBindings,
+    confirm: ActionBindings,
+    back: ActionBindings,
+}
+
+impl Default for Controls {
+    fn default() -> Self {
+        use GamepadButtonType::*;
+        Self {
+            up: ActionBindings::new(KeyCode::ArrowUp, KeyCode::KeyW, DPadUp),
+            down: ActionBindings::new(KeyCode::ArrowDown, KeyCode::KeyS, DPadDown),
+            left: ActionBindings::new(KeyCode::ArrowLeft, KeyCode::KeyA, DPadLeft),
+            right: ActionBindings::new(KeyCode::ArrowRight, KeyCode::KeyD, DPadRight),
+            // Escape pauses like it always did, so going back has keys of its own
+            pause: ActionBindings::new(KeyCode::Escape, KeyCode::KeyP, Start),
+            confirm: ActionBindings::new(KeyCode::Enter, KeyCode::Space, South),
+            back: ActionBindings::new(KeyCode::Backspace, KeyCode::Delete, East),
+        }
+    }
+}
+
+impl Controls {
+    pub fn bindings(&self, action: Action) -> &ActionBindings {
+        match action {
+            Action::Up => &self.up,
+            Action::Down => &self.down,
+            Action::Left => &self.left,
+            Action::Right => &self.right,
+            Action::Pause => &self.pause,
+            Action::Confirm => &self.confirm,
+            Action::Back => &self.back,
+        }
+    }
+
+    pub fn bindings_mut(&mut self, action: Action) -> &mut ActionBindings {
+        match action {
+            Action::Up => &mut self.up,
+            Action::Down => &mut self.down,
+            Action::Left => &mut self.left,
+            Action::Right => &mut self.right,
+            Action::Pause => &mut self.pause,
+            Action::Confirm => &mut self.confirm,
+            Action::Back => &mut self.back,
+        }
+    }
+
+    /// Binds a key to a slot, the slot already using it gets the key it replaces instead.
+    pub fn bind_key(&mut self, action: Action, index: usize, key: KeyCode) {
+        let replaced = self.bindings(action).keys[index];
+        for other in Action::ALL {
+            for slot in &mut self.bindings_mut(other).keys {
+                if *slot == Some(key) {
+                    *slot = replaced;
+                }
+            }
+        }
+        self.bindings_mut(action).keys[index] = Some(key);
+    }
+
+    /// Binds a gamepad button to an action, the action already using it gets the button it replaces instead.
+    pub fn bind_button(&mut self, action: Action, button: GamepadButtonType) {
+        let replaced = self.bindings(action).button;
+        for other in Action::ALL {
+            let slot = &mut self.bindings_mut(other).button;
+            if *slot == Some(button) {
+                *slot = replaced;
+            }
+        }
+        self.bindings_mut(action).button = Some(button);
+    }
+}
+
+/// State of every action this frame, read by the game instead of the raw inputs.
+#[derive(Resource, Debug, Default)]
+pub struct ActionInput {
+    pressed: Vec<Action>,
+    just_pressed: Vec<Action>,
+    // The same presses with the player they belong to in a versus game, gamepads going to players in order
+    player_presses: Vec<(Action, usize)>,
+    // Stick directions held on each gamepad, so one player's stick doesn't hide another's press
+    held_sticks: Vec<(Gamepad, Action)>,
+}
+
+impl ActionInput {
+    pub fn pressed(&self, action: Action) -> bool {
+        self.pressed.contains(&action)
+    }
+
+    pub fn just_pressed(&self, action: Action) -> bool {
+        self.just_pressed.contains(&action)
+    }
+
+    /// Actions started this frame, in the order they were pressed.
+    pub fn iter_just_pressed(&self) -> impl Iterator<Item = Action> + '_ {
+        self.just_pressed.iter().copied()
+    }
+
+    /// Actions one player of a versus game started this frame, in the order they were pressed.
+    pub fn iter_just_pressed_by(&self, player: usize) -> impl Iterator<Item = Action> + '_ {
+        self.player_presses
+            .iter()
+            .filter(move |(_, by)| *by == player)
+            .map(|(action, _)| *action)
+    }
+}
+
+fn update_action_input(
+    controls: Res<Controls>,
+    mut keyboard_events: EventReader<KeyboardInput>,
+    keys: Res<ButtonInput<KeyCode>>,
+    gamepads: Res<Gamepads>,
+    buttons: Res<ButtonInput<GamepadButton>>,
+    axes: Res<Axis<GamepadAxis>>,
+    mut input: ResMut<ActionInput>,
+) {
+    let previous_sticks = std::mem::take(&mut input.held_sticks);
+    input.pressed.clear();
+    input.just_pressed.clear();
+    input.player_presses.clear();
+
+    // Key events keep the order of presses made within a single frame, key repeats are not presses
+    for event in keyboard_events.read() {
+        if !keys.just_pressed(event.key_code) {
+            continue;
+        }
+        for action in Action::ALL {
+            let keys = &controls.bindings(action).keys;
+            let Some(slot) = keys.iter().position(|key| *key == Some(event.key_code)) else {
+                continue;
+            };
+            if !input.just_pressed.contains(&action) {
+                input.just_pressed.push(action);
+            }
+            if let Some(player) = VERSUS_KEY_SLOTS.iter().position(|versus| *versus == slot) {
+                input.player_presses.push((action, player));
+            }
+        }
+    }
+
+    for action in Action::ALL {
+        let bindings = controls.bindings(action);
+        let mut pressed = bindings.keys.iter().flatten().any(|key| keys.pressed(*key));
+        let mut just_pressed = false;
+        for (player, gamepad) in gamepads.iter().enumerate() {
+            let mut pad_just_pressed = false;
+            if let Some(button) = bindings.button {
+                let button = GamepadButton::new(gamepad, button);
+                pressed |= buttons.pressed(button);
+                pad_just_pressed |= buttons.just_pressed(button);
+            }
+            if let Some((axis, sign)) = action.stick() {
+                let value = axes.get(GamepadAxis::new(gamepad, axis)).unwrap_or(0.);
+                if value * sign > STICK_THRESHOLD {
+                    pressed = true;
+                    pad_just_pressed |= !previous_sticks.contains(&(gamepad, action));
+                    input.held_sticks.push((gamepad, action));
+                }
+            }
+            if pad_just_pressed {
+                just_pressed = true;
+                input.player_presses.push((action, player));
+            }
+        }
+
+        if pressed {
+            input.pressed.push(action);
+        }
+        if just_pressed && !input.just_pressed.contains(&action) {
+            input.just_pressed.push(action);
+        }
+    }
+}
@@ -0,0 +1,76 @@
+use bevy::prelude::*;
+use rand::{seq::SliceRandom, Rng};
+
+use crate::{
+    controls::ActionInput,
+    game::snake::Simulation,
+    sim::{ai::Strategy, level::Level},
+    utils::{Arena, GameState},
+};
+
+/// Game time after which a demo makes way for the splash screen again.
+const DEMO_SECONDS: f32 = 40.;
+
+/// Board of the demos, with an even side so the Hamiltonian player can go around it.
+pub const DEMO_ARENA: Arena = Arena::MEDIUM;
+
+/// Present while computer players show the game off, in turns with the splash screen until a key is pressed.
+#[derive(Resource, Debug)]
+pub struct Demo {
+    /// One computer player per snake.
+    pub strategies: Vec<Strategy>,
+}
+
+impl Demo {
+    /// A lone snake or a match between two, played by random strategies that suit the game.
+    pub fn random() -> Self {
+        let mut rng = rand::thread_rng();
+        let players = rng.gen_range(1..=2);
+        let level = Level::open(DEMO_ARENA);
+        let strategies = Strategy::ALL
+            .into_iter()
+            .filter(|strategy| strategy.supports(&level, players))
+            .collect::<Vec<_>>();
+        Self {
+            strategies: (0..players)
+                .filter_map(|_| strategies.choose(&mut rng).copied())
+                .collect(),
+        }
+    }
+}
+
+pub fn demo_plugin(app: &mut App) {
+    app.add_systems(
+        Update,
+        (
+            leave_demo.run_if(
+                in_state(GameState::Splash)
+                    .or_else(in_state(GameState::Game).and_then(resource_exists::<Demo>)),
+            ),
+            end_demo.run_if(in_state(GameState::Game).and_then(resource_exists::<Demo>)),
+        ),
+    )
+    .add_systems(OnExit(GameState::Game), remove_demo);
+}
+
+// Any key or gamepad button brings the menu up
+fn leave_demo(
+    keys: Res<ButtonInput<KeyCode>>,
+    actions: Res<ActionInput>,
+    mut game_state: ResMut<NextState<GameState>>,
+) {
+    if keys.get_just_pressed().next().is_some() || actions.iter_just_pressed().next().is_some() {
+        game_state.set(GameState::Menu);
+    }
+}
+
+// The Hamiltonian player never dies, so demos are cut short
+fn end_demo(simulation: Res<Simulation>, mut game_state: ResMut<NextState<GameState>>) {
+    if simulation.elapsed() >= DEMO_SECONDS {
+        game_state.set(GameState::Splash);
+    }
+}
+
+fn remove_demo(mut commands: Commands) {
+    commands.remove_resource::<Demo>();
+}
@@ -0,0 +1,724 @@
+use bevy::prelude::*;
+
+use crate::{
+    controls::{Action, ActionInput},
+    game::{
+        food::Food,
+        game::{window_to_grid, HUD_HEIGHT},
+        obstacle::OBSTACLE_COLOR,
+        snake::{Simulation, SNAKE_HEAD_COLOR, SNAKE_SEGMENT_COLOR},
+    },
+    menu::{
+        button_system, focus_navigation, release_focus_press, setting_button, SelectedOption,
+        NORMAL_BUTTON,
+    },
+    sim::{level::Level, world::SnakeWorld},
+    utils::{
+        despawn_screen, Arena, Direction, GameState, Position, SelectedLevel, Size, TEXT_COLOR,
+    },
+};
+
+// Clockwise, clicking the snake head turns it to the next one that fits
+const TURNS: [Direction; 4] = [
+    Direction::Up,
+    Direction::Right,
+    Direction::Down,
+    Direction::Left,
+];
+
+pub fn editor_plugin(app: &mut App) {
+    app.add_sub_state::<EditorPanel>()
+        .add_systems(OnEnter(GameState::Editor), (editor_setup, spawn_toolbar))
+        .add_systems(
+            Update,
+            (
+                (setting_button::<EditorTool>, paint_cells).run_if(in_state(EditorPanel::Hidden)),
+                toggle_panel,
+                sync_edited_level,
+                button_system,
+            )
+                .chain()
+                .run_if(in_state(GameState::Editor)),
+        )
+        .add_systems(OnEnter(EditorPanel::Shown), panel_setup)
+        .add_systems(
+            Update,
+            (
+                release_focus_press,
+                focus_navigation,
+                panel_action,
+                update_panel_texts,
+            )
+                .chain()
+                .run_if(in_state(EditorPanel::Shown)),
+        )
+        .add_systems(OnExit(EditorPanel::Shown), despawn_screen::<OnEditorPanel>)
+        .add_systems(OnExit(GameState::Editor), despawn_screen::<OnEditorScreen>);
+}
+
+/// Menu over the editor with everything that doesn't fit in the toolbar.
+#[derive(Clone, Copy, Default, Eq, PartialEq, Debug, Hash, SubStates)]
+#[source(GameState = GameState::Editor)]
+enum EditorPanel {
+    #[default]
+    Hidden,
+    Shown,
+}
+
+/// Level played from the editor, which it goes back to once the game is over.
+#[derive(Resource)]
+pub struct TestPlay(pub Level);
+
+#[derive(Resource, Deref, DerefMut, PartialEq)]
+struct EditedLevel(Level);
+
+// What a left click paints, a right click always erases
+#[derive(Resource, Component, Clone, Copy, Default, PartialEq, Eq, Debug)]
+enum EditorTool {
+    #[default]
+    Wall,
+    Food,
+    Snake,
+    Erase,
+}
+
+impl EditorTool {
+    const ALL: [EditorTool; 4] = [
+        EditorTool::Wall,
+        EditorTool::Food,
+        EditorTool::Snake,
+        EditorTool::Erase,
+    ];
+
+    fn name(self) -> &'static str {
+        match self {
+            EditorTool::Wall => "Wall",
+            EditorTool::Food => "Food",
+            EditorTool::Snake => "Snake",
+            EditorTool::Erase => "Erase",
+        }
+    }
+}
+
+#[derive(Component)]
+struct OnEditorScreen;
+
+#[derive(Component)]
+struct OnEditorPanel;
+
+#[derive(Component)]
+struct EditorTile;
+
+#[derive(Component)]
+enum EditorButtonAction {
+    OpenPanel,
+    ClosePanel,
+    Save,
+    TestPlay,
+    Exit,
+}
+
+// Grows or shrinks the board by this many cells
+#[derive(Component)]
+enum ResizeButton {
+    Width(i32),
+    Height(i32),
+}
+
+#[derive(Component)]
+struct LevelSizeText;
+
+#[derive(Component)]
+struct EditorStatusText;
+
+// Starts from the level played last when coming back from a test, else from the one picked in the menu
+fn editor_setup(
+    mut commands: Commands,
+    test_play: Option<Res<TestPlay>>,
+    selected_level: Res<SelectedLevel>,
+    arena: Res<Arena>,
+) {
+    let level = match (test_play, &selected_level.0) {
+        (Some(test_play), _) => test_play.0.clone(),
+        (None, Some(name)) => Level::load_named(name).unwrap_or_else(|err| {
+            warn!("Could not load level {name}, starting a new one: {err}");
+            Level::open(*arena)
+        }),
+        (None, None) => Level::open(*arena),
+    };
+    commands.remove_resource::<TestPlay>();
+    commands.insert_resource(EditedLevel(level));
+    commands.insert_resource(EditorTool::default());
+    commands.insert_resource(ClearColor(Color::srgb(0.04, 0.04, 0.04)));
+}
+
+fn spawn_toolbar(mut commands: Commands) {
+    let button_style = Style {
+        width: Val::Px(70.0),
+        height: Val::Px(34.0),
+        margin: UiRect::horizontal(Val::Px(3.0)),
+        justify_content: JustifyContent::Center,
+        align_items: AlignItems::Center,
+        ..default()
+    };
+    let button_text_style = TextStyle {
+        font_size: 20.0,
+        color: TEXT_COLOR,
+        ..default()
+    };
+
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    width: Val::Percent(100.),
+                    height: Val::Px(HUD_HEIGHT),
+                    align_items: AlignItems::Center,
+                    padding: UiRect::horizontal(Val::Px(6.)),
+                    ..default()
+                },
+                background_color: Color::srgb(0.2, 0.2, 0.2).into(),
+                ..default()
+            },
+            OnEditorScreen,
+        ))
+        .with_children(|parent| {
+            for tool in EditorTool::ALL {
+                let mut entity = parent.spawn((
+                    ButtonBundle {
+                        style: button_style.clone(),
+                        background_color: NORMAL_BUTTON.into(),
+                        ..default()
+                    },
+                    tool,
+                ));
+                entity.with_children(|parent| {
+                    parent.spawn(TextBundle::from_section(
+                        tool.name(),
+                        button_text_style.clone(),
+                    ));
+                });
+                if tool == EditorTool::default() {
+                    entity.insert(SelectedOption);
+                }
+            }
+            parent
+                .spawn((
+                    ButtonBundle {
+                        style: Style {
+                            margin: UiRect::left(Val::Auto),
+                            ..button_style.clone()
+                        },
+                        background_color: NORMAL_BUTTON.into(),
+                        ..default()
+                    },
+                    EditorButtonAction::OpenPanel,
+                ))
+                .with_children(|parent| {
+                    parent.spawn(TextBundle::from_section("Menu", button_text_style.clone()));
+                });
+        });
+}
+
+fn toggle_panel(
+    actions: Res<ActionInput>,
+    panel: Res<State<EditorPanel>>,
+    mut next_panel: ResMut<NextState<EditorPanel>>,
+    interaction_query: Query<
+        (&Interaction, &EditorButtonAction),
+        (Changed<Interaction>, With<Button>),
+    >,
+) {
+    let clicked = interaction_query.iter().any(|(interaction, action)| {
+        *interaction == Interaction::Pressed
+            && matches!(
+                action,
+                EditorButtonAction::OpenPanel | EditorButtonAction::ClosePanel
+            )
+    });
+    if !clicked && !actions.just_pressed(Action::Back) {
+        return;
+    }
+
+    next_panel.set(match panel.get() {
+        EditorPanel::Hidden => EditorPanel::Shown,
+        EditorPanel::Shown => EditorPanel::Hidden,
+    });
+}
+
+fn paint_cells(
+    windows: Query<&Window>,
+    mouse: Res<ButtonInput<MouseButton>>,
+    tool: Res<EditorTool>,
+    mut level: ResMut<EditedLevel>,
+    // Tool of the stroke in progress, strokes only start on the board
+    mut stroke: Local<Option<EditorTool>>,
+) {
+    if !mouse.any_pressed([MouseButton::Left, MouseButton::Right]) {
+        *stroke = None;
+    }
+    let window = windows.single();
+    let Some(position) = window
+        .cursor_position()
+        .and_then(|cursor| window_to_grid(window, level.arena, cursor))
+    else {
+        return;
+    };
+
+    let just_pressed = mouse.any_just_pressed([MouseButton::Left, MouseButton::Right]);
+    if mouse.just_pressed(MouseButton::Right) {
+        *stroke = Some(EditorTool::Erase);
+    } else if mouse.just_pressed(MouseButton::Left) {
+        *stroke = Some(*tool);
+    }
+    let Some(tool) = *stroke else {
+        return;
+    };
+    // Walls, food and erasing follow the cursor while held, the snake moves on clicks only
+    if tool == EditorTool::Snake && !just_pressed {
+        return;
+    }
+
+    let mut edited = level.0.clone();
+    paint(&mut edited, tool, position);
+    level.set_if_neq(EditedLevel(edited));
+}
+
+fn paint(level: &mut Level, tool: EditorTool, position: Position) {
+    let snake = [level.spawn, level.tail()];
+    match tool {
+        EditorTool::Wall if !snake.contains(&position) => {
+            if !level.obstacles.contains(&position) {
+                level.obstacles.push(position);
+            }
+            level.food_spots.retain(|spot| *spot != position);
+        }
+        EditorTool::Food if !snake.contains(&position) => {
+            if !level.food_spots.contains(&position) {
+                level.food_spots.push(position);
+            }
+            level.obstacles.retain(|obstacle| *obstacle != position);
+        }
+        EditorTool::Snake if position == level.spawn => {
+            let current = TURNS.iter().position(|turn| *turn == level.direction);
+            let next = current.map_or(0, |index| index + 1);
+            if let Some(direction) = (0..TURNS.len())
+                .map(|offset| TURNS[(next + offset) % TURNS.len()])
+                .find(|direction| fits(level, *direction))
+            {
+                level.direction = direction;
+                place_snake(level);
+            }
+        }
+        EditorTool::Snake => {
+            level.spawn = position;
+            place_snake(level);
+        }
+        EditorTool::Erase => {
+            level.obstacles.retain(|obstacle| *obstacle != position);
+            level.food_spots.retain(|spot| *spot != position);
+        }
+        EditorTool::Wall | EditorTool::Food => {}
+    }
+}
+
+// Cell behind the head if the snake faced this way, possibly off the board
+fn tail_towards(level: &Level, direction: Direction) -> Position {
+    Level {
+        spawn: level.spawn,
+        direction,
+        ..Level::open(level.arena)
+    }
+    .tail()
+}
+
+// Whether the snake has room on the board facing this way
+fn fits(level: &Level, direction: Direction) -> bool {
+    let tail = tail_towards(level, direction);
+    level.arena.contains(tail) && !level.obstacles.contains(&tail)
+}
+
+// Clears the cells under the snake, turning it when its tail would be off the board
+fn place_snake(level: &mut Level) {
+    level.spawn = Position {
+        x: level.spawn.x.min(level.arena.width as i32 - 1),
+        y: level.spawn.y.min(level.arena.height as i32 - 1),
+    };
+    // A board is always wide enough for the tail in some direction, walls can make room
+    let directions = [level.direction].into_iter().chain(TURNS);
+    level.direction = directions
+        .clone()
+        .find(|direction| fits(level, *direction))
+        .or_else(|| {
+            directions
+                .clone()
+                .find(|direction| level.arena.contains(tail_towards(level, *direction)))
+        })
+        .unwrap_or(level.direction);
+
+    let snake = [level.spawn, level.tail()];
+    level.obstacles.retain(|obstacle| !snake.contains(obstacle));
+    level.food_spots.retain(|spot| !snake.contains(spot));
+}
+
+fn resize(level: &mut Level, width: u32, height: u32) {
+    let arena = Arena::new(
+        width.clamp(Arena::MIN_SIZE, Arena::MAX_SIZE),
+        height.clamp(Arena::MIN_SIZE, Arena::MAX_SIZE),
+    );
+    level.arena = arena;
+    level.obstacles.retain(|obstacle| arena.contains(*obstacle));
+    level.food_spots.retain(|spot| arena.contains(*spot));
+    place_snake(level);
+}
+
+// Redraws the whole level whenever it is edited, mirrored in the simulation for the board layout
+fn sync_edited_level(
+    mut commands: Commands,
+    level: Res<EditedLevel>,
+    tiles: Query<Entity, With<EditorTile>>,
+) {
+    if !level.is_changed() {
+        return;
+    }
+
+    for entity in &tiles {
+        commands.entity(entity).despawn();
+    }
+    commands.insert_resource(Simulation(SnakeWorld::from_level(&level)));
+
+    let obstacles = level
+        .obstacles
+        .iter()
+        .map(|position| (*position, OBSTACLE_COLOR, 1.0));
+    let food = level
+        .food_spots
+        .iter()
+        .map(|position| (*position, Food::Grow.color(), 0.8));
+    let snake = [
+        (level.spawn, SNAKE_HEAD_COLOR, 0.8),
+        (level.tail(), SNAKE_SEGMENT_COLOR, 0.65),
+    ];
+    for (position, color, size) in obstacles.chain(food).chain(snake) {
+        commands.spawn((
+            SpriteBundle {
+                sprite: Sprite { color, ..default() },
+                ..default()
+            },
+            OnEditorScreen,
+            EditorTile,
+            position,
+            Size::square(size),
+        ));
+    }
+}
+
+fn panel_setup(mut commands: Commands, level: Res<EditedLevel>) {
+    let button_style = Style {
+        width: Val::Px(200.0),
+        height: Val::Px(45.0),
+        margin: UiRect::all(Val::Px(5.0)),
+        justify_content: JustifyContent::Center,
+        align_items: AlignItems::Center,
+        ..default()
+    };
+    let small_button_style = Style {
+        width: Val::Px(40.0),
+        height: Val::Px(40.0),
+        margin: UiRect::all(Val::Px(5.0)),
+        justify_content: JustifyContent::Center,
+        align_items: AlignItems::Center,
+        ..default()
+    };
+    let button_text_style = TextStyle {
+        font_size: 26.0,
+        color: TEXT_COLOR,
+        ..default()
+    };
+    let info_text_style = TextStyle {
+        font_size: 18.0,
+        color: TEXT_COLOR,
+        ..default()
+    };
+
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    width: Val::Percent(100.0),
+                    height: Val::Percent(100.0),
+                    align_items: AlignItems::Center,
+                    justify_content: JustifyContent::Center,
+                    ..default()
+                },
+                ..default()
+            },
+            OnEditorPanel,
+        ))
+        .with_children(|parent| {
+            parent
+                .spawn(NodeBundle {
+                    style: Style {
+                        flex_direction: FlexDirection::Column,
+                        align_items: AlignItems::Center,
+                        padding: UiRect::all(Val::Px(10.0)),
+                        ..default()
+                    },
+                    background_color: bevy::color::palettes::css::CRIMSON.into(),
+                    ..default()
+                })
+                .with_children(|parent| {
+                    parent.spawn((
+                        TextBundle::from_section(size_label(&level), button_text_style.clone()),
+                        LevelSizeText,
+                    ));
+                    parent.spawn(NodeBundle::default()).with_children(|parent| {
+                        for (button, label) in [
+                            (ResizeButton::Width(-1), "W-"),
+                            (ResizeButton::Width(1), "W+"),
+                            (ResizeButton::Height(-1), "H-"),
+                            (ResizeButton::Height(1), "H+"),
+                        ] {
+                            parent
+                                .spawn((
+                                    ButtonBundle {
+                                        style: Style {
+                                            width: Val::Px(50.0),
+                                            ..small_button_style.clone()
+                                        },
+                                        background_color: NORMAL_BUTTON.into(),
+                                        ..default()
+                                    },
+                                    button,
+                                ))
+                                .with_children(|parent| {
+                                    parent.spawn(TextBundle::from_section(
+                                        label,
+                                        button_text_style.clone(),
+                                    ));
+                                });
+                        }
+                    });
+
+                    for (action, text) in [
+                        (EditorButtonAction::Save, "Save"),
+                        (EditorButtonAction::TestPlay, "Test play"),
+                        (EditorButtonAction::ClosePanel, "Keep editing"),
+                        (EditorButtonAction::Exit, "Exit"),
+                    ] {
+                        parent
+                            .spawn((
+                                ButtonBundle {
+                                    style: button_style.clone(),
+                                    background_color: NORMAL_BUTTON.into(),
+                                    ..default()
+                                },
+                                action,
+                            ))
+                            .with_children(|parent| {
+                                parent.spawn(TextBundle::from_section(
+                                    text,
+                                    button_text_style.clone(),
+                                ));
+                            });
+                    }
+
+                    parent.spawn((
+                        TextBundle::from_section(
+                            "Left click paints, right click erases, click the head to turn it",
+                            info_text_style,
+                        )
+                        .with_text_justify(JustifyText::Center)
+                        .with_style(Style {
+                            max_width: Val::Px(300.0),
+                            margin: UiRect::top(Val::Px(5.0)),
+                            ..default()
+                        }),
+                        EditorStatusText,
+                    ));
+                });
+        });
+}
+
+fn size_label(level: &Level) -> String {
+    let name = if level.name.is_empty() {
+        "New level"
+    } else {
+        &level.name
+    };
+    format!("{name} {} x {}", level.arena.width, level.arena.height)
+}
+
+fn panel_action(
+    interaction_query: Query<
+        (&Interaction, &EditorButtonAction),
+        (Changed<Interaction>, With<Button>),
+    >,
+    resize_query: Query<(&Interaction, &ResizeButton), (Changed<Interaction>, With<Button>)>,
+    mut commands: Commands,
+    mut level: ResMut<EditedLevel>,
+    mut selected_level: ResMut<SelectedLevel>,
+    mut status_texts: Query<&mut Text, With<EditorStatusText>>,
+    mut game_state: ResMut<NextState<GameState>>,
+) {
+    for (interaction, resize_button) in &resize_query {
+        if *interaction != Interaction::Pressed {
+            continue;
+        }
+        let arena = level.arena;
+        let (width, height) = match resize_button {
+            ResizeButton::Width(delta) => (arena.width.saturating_add_signed(*delta), arena.height),
+            ResizeButton::Height(delta) => {
+                (arena.width, arena.height.saturating_add_signed(*delta))
+            }
+        };
+        let mut edited = level.0.clone();
+        resize(&mut edited, width, height);
+        level.set_if_neq(EditedLevel(edited));
+    }
+
+    for (interaction, action) in &interaction_query {
+        if *interaction != Interaction::Pressed {
+            continue;
+        }
+        match action {
+            // Handled by `toggle_panel`, along with the Back action
+            EditorButtonAction::OpenPanel | EditorButtonAction::ClosePanel => {}
+            EditorButtonAction::Save => {
+                // New levels and bundled ones are saved under a new name
+                let status = match level.save() {
+                    Ok(path) => {
+                        selected_level.0 = Some(level.name.clone());
+                        format!("Saved to {}", path.display())
+                    }
+                    Err(err) => format!("Could not save: {err}"),
+                };
+                for mut text in &mut status_texts {
+                    text.sections[0].value = status.clone();
+                }
+            }
+            EditorButtonAction::TestPlay => {
+                commands.insert_resource(TestPlay(level.0.clone()));
+                game_state.set(GameState::Game);
+            }
+            EditorButtonAction::Exit => game_state.set(GameState::Menu),
+        }
+    }
+}
+
+fn update_panel_texts(level: Res<EditedLevel>, mut texts: Query<&mut Text, With<LevelSizeText>>) {
+    if !level.is_changed() {
+        return;
+    }
+
+    for mut text in &mut texts {
+        text.sections[0].value = size_label(&level);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(x: i32, y: i32) -> Position {
+        Position { x, y }
+    }
+
+    // The open 10x10 board, the snake at (3, 3) heading up with its tail at (3, 2)
+    fn level() -> Level {
+        Level::open(Arena::SMALL)
+    }
+
+    #[test]
+    fn walls_and_food_replace_each_other() {
+        let mut level = level();
+        paint(&mut level, EditorTool::Wall, at(5, 5));
+        paint(&mut level, EditorTool::Wall, at(5, 5));
+        assert_eq!(level.obstacles, [at(5, 5)]);
+
+        paint(&mut level, EditorTool::Food, at(5, 5));
+        assert!(level.obstacles.is_empty());
+        assert_eq!(level.food_spots, [at(5, 5)]);
+
+        paint(&mut level, EditorTool::Erase, at(5, 5));
+        assert!(level.food_spots.is_empty());
+    }
+
+    #[test]
+    fn the_snake_cells_cannot_be_painted() {
+        let mut level = level();
+        paint(&mut level, EditorTool::Wall, at(3, 3));
+        paint(&mut level, EditorTool::Food, at(3, 2));
+        assert!(level.obstacles.is_empty());
+        assert!(level.food_spots.is_empty());
+    }
+
+    #[test]
+    fn clicking_the_head_turns_the_snake_to_the_next_free_side() {
+        let mut level = level();
+        // Right is blocked, food on the cell behind a snake heading down is cleared
+        paint(&mut level, EditorTool::Wall, at(2, 3));
+        paint(&mut level, EditorTool::Food, at(3, 4));
+        paint(&mut level, EditorTool::Snake, at(3, 3));
+        assert_eq!(level.direction, Direction::Down);
+        assert_eq!(level.tail(), at(3, 4));
+        assert!(level.food_spots.is_empty());
+        assert!(Level::parse(&level.encode()).is_ok());
+    }
+
+    #[test]
+    fn moving_the_snake_clears_the_cells_under_it() {
+        let mut level = level();
+        paint(&mut level, EditorTool::Wall, at(6, 6));
+        paint(&mut level, EditorTool::Food, at(6, 5));
+        paint(&mut level, EditorTool::Snake, at(6, 6));
+        assert_eq!(level.spawn, at(6, 6));
+        assert_eq!(level.tail(), at(6, 5));
+        assert!(level.obstacles.is_empty());
+        assert!(level.food_spots.is_empty());
+    }
+
+    #[test]
+    fn a_snake_on_the_edge_turns_to_fit() {
+        let mut level = level();
+        paint(&mut level, EditorTool::Snake, at(4, 0));
+        assert_eq!(level.spawn, at(4, 0));
+        assert!(level.arena.contains(level.tail()));
+        assert_ne!(level.direction, Direction::Up);
+    }
+
+    #[test]
+    fn resize_drops_what_no_longer_fits() {
+        let mut level = level();
+        paint(&mut level, EditorTool::Wall, at(1, 1));
+        paint(&mut level, EditorTool::Wall, at(8, 1));
+        paint(&mut level, EditorTool::Food, at(1, 8));
+        resize(&mut level, 6, 6);
+        assert_eq!(level.arena, Arena::new(6, 6));
+        assert_eq!(level.obstacles, [at(1, 1)]);
+        assert!(level.food_spots.is_empty());
+
+        resize(&mut level, 1, 100);
+        assert_eq!(level.arena, Arena::new(Arena::MIN_SIZE, Arena::MAX_SIZE));
+    }
+
+    #[test]
+    fn shrinking_under_the_snake_brings_it_back_on_the_board() {
+        let mut level = level();
+        paint(&mut level, EditorTool::Snake, at(8, 8));
+        paint(&mut level, EditorTool::Snake, at(8, 8));
+        paint(&mut level, EditorTool::Snake, at(8, 8));
+        assert_eq!(level.direction, Direction::Down);
+        // Walls on both free sides of the corner the snake lands in
+        paint(&mut level, EditorTool::Wall, at(4, 3));
+        paint(&mut level, EditorTool::Wall, at(3, 4));
+
+        resize(&mut level, 5, 5);
+        assert_eq!(level.spawn, at(4, 4));
+        // Heading down or left the tail would be off the board, up it clears the wall
+        assert_eq!(level.direction, Direction::Up);
+        assert_eq!(level.tail(), at(4, 3));
+        assert_eq!(level.obstacles, [at(3, 4)]);
+        assert!(Level::parse(&level.encode()).is_ok());
+    }
+}
@@ -1,11 +1,22 @@
 use bevy::{dev_tools::states::*, prelude::*};
+use rand::{rngs::StdRng, SeedableRng};
 
 use crate::{
-    game::{food::*, snake::*},
-    utils::{despawn_screen, GameState, Position, Size, ARENA_HEIGHT, ARENA_WIDTH, TEXT_COLOR},
+    campaign::{campaign_goal, CampaignRun},
+    controls::{Action, ActionInput},
+    demo::{Demo, DEMO_ARENA},
+    editor::TestPlay,
+    game::{food::*, obstacle::spawn_obstacles, replay::*, snake::*},
+    high_scores::{score_bucket, HighScores, PendingHighScore},
+    net::NetSession,
+    sim::{ai::Strategy, level::Level, replay::Replay},
+    utils::{
+        despawn_screen, Arena, GameMode, GameState, Players, Position, Seed, SelectedLevel, Size,
+        TEXT_COLOR,
+    },
 };
 
-use super::sound::{self, FoodEatenPitchEvent};
+use super::sound::{SoundEffect, SoundEffectEvent};
 
 #[derive(Debug, Clone, Copy, Default, Eq, PartialEq, Hash, SubStates)]
 #[source(GameState = GameState::Game)]
@@ -13,101 +24,226 @@ enum InGameState {
     #[default]
     Running,
     Paused,
+    Dying,
 }
 
 #[derive(Event)]
 pub struct GameOverEvent;
 
+#[derive(Event)]
+pub struct GameWonEvent;
+
 #[derive(Component)]
 pub struct OnGameScreen;
 
+/// Score of one player in the score bar.
 #[derive(Component)]
-pub struct ScoreText;
+pub struct ScoreText(pub usize);
 
-#[derive(Resource)]
-pub struct Hud {
-    pub score: u32,
-}
+/// The opponent of a game against the computer.
+const CPU_STRATEGY: Strategy = Strategy::Bfs;
+
+/// Height of the score bar, the arena is drawn in the rest of the window below it.
+pub const HUD_HEIGHT: f32 = 46.;
 
 #[derive(Component)]
 struct OnPauseScreen;
 
+const DEATH_ANIMATION_SECONDS: f32 = 1.2;
+const DEATH_BLINK_SECONDS: f32 = 0.15;
+const DEAD_HEAD_COLOR: Color = Color::srgb(0.8, 0.1, 0.1);
+
+#[derive(Resource, Deref, DerefMut)]
+struct DeathAnimationTimer(Timer);
+
+/// Every random draw of a game comes from here, so the same seed and inputs replay the same game.
+#[derive(Resource, Deref, DerefMut)]
+pub struct GameRng {
+    pub seed: u64,
+    #[deref]
+    rng: StdRng,
+}
+
+impl GameRng {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            seed,
+            rng: StdRng::seed_from_u64(seed),
+        }
+    }
+}
+
 pub fn game_plugin(app: &mut App) {
     app.add_sub_state::<InGameState>()
         .enable_state_scoped_entities::<InGameState>()
-        .add_event::<GrowthEvent>()
-        .add_event::<ShrinkEvent>()
         .add_event::<GameOverEvent>()
-        .add_event::<FoodEatenPitchEvent>()
+        .add_event::<GameWonEvent>()
         .add_systems(
             OnEnter(GameState::Game),
-            (init_game_resources, spawn_score_hud, spawn_snake).chain(),
+            (
+                init_game_resources,
+                spawn_score_hud,
+                spawn_obstacles,
+                spawn_snake,
+            )
+                .chain(),
         )
         .add_systems(Update, pause_menu.run_if(in_state(InGameState::Paused)))
         .add_systems(OnExit(InGameState::Paused), despawn_screen::<OnPauseScreen>)
+        .add_systems(OnEnter(InGameState::Dying), start_death_animation)
+        .add_systems(Update, death_animation.run_if(in_state(InGameState::Dying)))
         .add_systems(
             Update,
             (
                 (
                     snake_movement_input,
-                    snake_eating,
-                    snake_growth,
-                    snake_shrink,
                     snake_movement,
-                    sound::play_food_eaten_pitch,
+                    campaign_goal,
+                    sync_snake_segments,
+                    sync_food,
+                    update_score_text,
                     game_over,
-                    grow_food_spawner,
-                    shrink_food_spawner,
                 )
                     .chain()
                     .run_if(in_state(InGameState::Running)),
-                (toggle_pause.run_if(in_state(GameState::Game))),
+                (toggle_pause.run_if(
+                    in_state(GameState::Game)
+                        .and_then(not(resource_exists::<Playback>))
+                        .and_then(not(resource_exists::<Demo>))
+                        .and_then(not(resource_exists::<NetSession>)),
+                )),
+                (playback_controls, update_playback_text)
+                    .run_if(in_state(GameState::Game).and_then(resource_exists::<Playback>)),
             ),
         )
-        .add_systems(PostUpdate, (position_translation, size_scaling))
+        .add_systems(
+            PostUpdate,
+            (position_translation, size_scaling).run_if(resource_exists::<Simulation>),
+        )
         .add_systems(Update, log_transitions::<GameState>)
-        .add_systems(OnExit(GameState::Game), despawn_screen::<OnGameScreen>);
+        .add_systems(
+            OnExit(GameState::Game),
+            (despawn_screen::<OnGameScreen>, remove_playback),
+        );
 }
 
-fn init_game_resources(mut commands: Commands) {
+fn init_game_resources(
+    mut commands: Commands,
+    seed: Res<Seed>,
+    mode: Res<GameMode>,
+    players: Res<Players>,
+    arena: Res<Arena>,
+    selected_level: Res<SelectedLevel>,
+    campaign: Option<Res<CampaignRun>>,
+    test_play: Option<Res<TestPlay>>,
+    playback: Option<Res<Playback>>,
+    demo: Option<Res<Demo>>,
+    mut net: Option<ResMut<NetSession>>,
+) {
+    // Demos are never the same twice, whatever the seed setting
+    let seed = playback
+        .as_ref()
+        .map(|playback| playback.seed())
+        .or(net.as_ref().map(|net| net.setup().seed))
+        .or(seed.0.filter(|_| demo.is_none()))
+        .unwrap_or_else(rand::random);
+    let mode = match (&playback, &demo, &net) {
+        (Some(playback), _, _) => playback.mode(),
+        (None, Some(_), _) => GameMode::Classic,
+        (None, None, Some(net)) => net.setup().mode,
+        (None, None, None) => *mode,
+    };
+    let level = if let Some(net) = &net {
+        Ok(net.setup().level.clone())
+    } else if demo.is_some() {
+        Ok(Level::open(DEMO_ARENA))
+    } else if let Some(playback) = &playback {
+        playback.load_level()
+    } else if let Some(test_play) = &test_play {
+        Ok(test_play.0.clone())
+    } else if let Some(campaign) = &campaign {
+        Ok(campaign.level.clone())
+    } else if let Some(name) = &selected_level.0 {
+        Level::load_named(name)
+    } else {
+        Ok(Level::open(*arena))
+    };
+    let level = level.unwrap_or_else(|err| {
+        warn!("Could not load the level, playing on the open arena: {err}");
+        Level::open(*arena)
+    });
+    // Campaign levels and levels being edited are played alone, replays only ever follow their inputs
+    let controllers: Vec<_> = if let Some(demo) = &demo {
+        demo.strategies
+            .iter()
+            .map(|strategy| Some(Cpu::new(*strategy)))
+            .collect()
+    } else if let Some(playback) = &playback {
+        (0..playback.players()).map(|_| None).collect()
+    } else if net.is_some() {
+        vec![None, None]
+    } else if campaign.is_some() || test_play.is_some() {
+        vec![None]
+    } else if *players == Players::Cpu {
+        vec![None, Some(Cpu::new(CPU_STRATEGY))]
+    } else {
+        (0..players.count()).map(|_| None).collect()
+    };
+    let players = controllers.len();
+    commands.insert_resource(SnakeControllers(controllers));
+    info!("Game seed: {seed}");
+    commands.insert_resource(GameRng::new(seed));
+
     commands.insert_resource(ClearColor(Color::srgb(0.04, 0.04, 0.04)));
-    commands.insert_resource(FoodSpawnTimer(Timer::from_seconds(
-        2.0,
-        TimerMode::Repeating,
-    )));
-    commands.insert_resource(ShrinkFoodSpawnTimer(Timer::from_seconds(
-        8.0,
-        TimerMode::Repeating,
-    )));
+    let recorder = Replay::new(seed, mode, level.arena)
+        .with_level(&level)
+        .with_players(players);
+    let simulation = Simulation(recorder.start(&level));
+    if let Some(net) = &mut net {
+        net.start(&simulation);
+    }
+    commands.insert_resource(ReplayRecorder(recorder));
     commands.insert_resource(SnakeDirectionTimer(Timer::from_seconds(
-        0.20,
+        simulation.tick_duration(),
         TimerMode::Repeating,
     )));
-    commands.insert_resource(LastTailPosition::default());
-    commands.insert_resource(SnakeSegments::default());
-    commands.insert_resource(Hud { score: 0 });
+    commands.insert_resource(simulation);
 }
 
 fn toggle_pause(
-    input: Res<ButtonInput<KeyCode>>,
+    actions: Res<ActionInput>,
     current_state: Res<State<InGameState>>,
     mut next_state: ResMut<NextState<InGameState>>,
+    mut sound_writer: EventWriter<SoundEffectEvent>,
 ) {
-    if input.just_pressed(KeyCode::Escape) {
-        next_state.set(match current_state.get() {
-            InGameState::Running => InGameState::Paused,
-            InGameState::Paused => InGameState::Running,
-        });
+    if actions.just_pressed(Action::Pause) {
+        match current_state.get() {
+            InGameState::Running => {
+                next_state.set(InGameState::Paused);
+                sound_writer.send(SoundEffectEvent(SoundEffect::Pause));
+            }
+            InGameState::Paused => next_state.set(InGameState::Running),
+            InGameState::Dying => {}
+        }
     }
 }
 
-fn spawn_score_hud(mut commands: Commands) {
+fn spawn_score_hud(
+    mut commands: Commands,
+    simulation: Res<Simulation>,
+    controllers: Res<SnakeControllers>,
+    playback: Option<Res<Playback>>,
+    campaign: Option<Res<CampaignRun>>,
+    demo: Option<Res<Demo>>,
+) {
+    // Games with a computer player or several snakes name each score
+    let versus = simulation.snakes().len() > 1 || controllers.is_cpu(0);
     commands
         .spawn((
             NodeBundle {
                 style: Style {
                     width: Val::Percent(100.),
-                    height: Val::Px(46.),
+                    height: Val::Px(HUD_HEIGHT),
                     padding: UiRect {
                         top: Val::Px(12.),
                         left: Val::Px(12.),
@@ -121,27 +257,101 @@ fn spawn_score_hud(mut commands: Commands) {
             OnGameScreen,
         ))
         .with_children(|parent| {
-            parent.spawn((
-                TextBundle::from_sections([
-                    TextSection::new(
-                        "Score: ",
+            // In versus each player gets a panel in the color of their snake
+            for player in 0..simulation.snakes().len() {
+                let (label, color) = if versus {
+                    (
+                        format!("{}: ", controllers.label(player)),
+                        snake_colors(player).0,
+                    )
+                } else {
+                    ("Score: ".to_string(), TEXT_COLOR)
+                };
+                parent.spawn((
+                    TextBundle::from_sections([
+                        TextSection::new(
+                            label,
+                            TextStyle {
+                                font_size: 24.,
+                                color,
+                                ..default()
+                            },
+                        ),
+                        TextSection::new(
+                            "0",
+                            TextStyle {
+                                font_size: 20.,
+                                color,
+                                ..default()
+                            },
+                        ),
+                    ])
+                    .with_style(Style {
+                        margin: UiRect::right(Val::Px(24.)),
+                        ..default()
+                    }),
+                    ScoreText(player),
+                ));
+            }
+            if demo.is_some() {
+                parent.spawn(
+                    TextBundle::from_section(
+                        "Press any key",
                         TextStyle {
-                            font_size: 24.,
+                            font_size: 20.,
                             color: TEXT_COLOR,
                             ..default()
                         },
-                    ),
-                    TextSection::new(
-                        "0",
+                    )
+                    .with_style(Style {
+                        margin: UiRect {
+                            left: Val::Auto,
+                            right: Val::Px(12.),
+                            ..default()
+                        },
+                        ..default()
+                    }),
+                );
+            } else if playback.is_some() {
+                parent.spawn((
+                    TextBundle::from_section(
+                        "Replay",
                         TextStyle {
                             font_size: 20.,
                             color: TEXT_COLOR,
                             ..default()
                         },
-                    ),
-                ]),
-                ScoreText,
-            ));
+                    )
+                    .with_style(Style {
+                        margin: UiRect {
+                            left: Val::Auto,
+                            right: Val::Px(12.),
+                            ..default()
+                        },
+                        ..default()
+                    }),
+                    PlaybackText,
+                ));
+            } else if let Some(campaign) = campaign {
+                parent.spawn(
+                    TextBundle::from_section(
+                        format!("Goal: {}", campaign.goal.describe()),
+                        TextStyle {
+                            font_size: 20.,
+                            color: TEXT_COLOR,
+                            ..default()
+                        },
+                    )
+                    .with_style(Style {
+                        margin: UiRect {
+                            left: Val::Auto,
+                            right: Val::Px(12.),
+                            ..default()
+                        },
+                        ..default()
+                    }),
+                );
+            }
         });
 }
 
@@ -193,44 +403,162 @@ fn pause_menu(mut commands: Commands) {
 
 fn game_over(
     mut reader: EventReader<GameOverEvent>,
-    // segments_res: ResMut<SnakeSegments>,
+    mut won_reader: EventReader<GameWonEvent>,
     // food: Query<Entity, With<Food>>,
     // segments: Query<Entity, With<SnakeSegment>>,
+    mut commands: Commands,
+    mut recorder: ResMut<ReplayRecorder>,
+    playback: Option<Res<Playback>>,
+    test_play: Option<Res<TestPlay>>,
+    demo: Option<Res<Demo>>,
+    simulation: Res<Simulation>,
+    high_scores: Res<HighScores>,
     mut game_state: ResMut<NextState<GameState>>,
+    mut in_game_state: ResMut<NextState<InGameState>>,
 ) {
-    if reader.read().next().is_some() {
-        game_state.set(GameState::Menu)
+    let lost = reader.read().next().is_some();
+    let won = won_reader.read().next().is_some();
+    if !lost && !won {
+        return;
+    }
+
+    // Watching a replay neither records it again nor enters the high scores, nor does trying a level being edited or a demo
+    if playback.is_none() && test_play.is_none() && demo.is_none() {
+        recorder.finish(&simulation);
+        match recorder.save() {
+            Ok(path) => info!("Replay saved to {}", path.display()),
+            Err(err) => warn!("Could not save the replay: {err}"),
+        }
+
+        // High scores are for players alone on the board
+        let bucket = score_bucket(simulation.mode(), simulation.arena(), simulation.level());
+        if simulation.snakes().len() == 1 && high_scores.qualifies(&bucket, simulation.score()) {
+            commands.insert_resource(PendingHighScore::new(bucket, &simulation));
+        }
+    }
+    if won {
+        game_state.set(end_screen(demo.is_some()));
+    } else {
+        in_game_state.set(InGameState::Dying);
     }
 }
 
+fn start_death_animation(
+    mut commands: Commands,
+    simulation: Res<Simulation>,
+    mut heads: Query<(&Player, &mut Sprite), With<SnakeHead>>,
+) {
+    commands.insert_resource(DeathAnimationTimer(Timer::from_seconds(
+        DEATH_ANIMATION_SECONDS,
+        TimerMode::Once,
+    )));
+    for (player, mut sprite) in &mut heads {
+        if simulation.snakes()[player.0].death().is_some() {
+            sprite.color = DEAD_HEAD_COLOR;
+        }
+    }
+}
+
+// Blinks the dead snakes for a moment before showing the run summary
+fn death_animation(
+    time: Res<Time>,
+    simulation: Res<Simulation>,
+    mut timer: ResMut<DeathAnimationTimer>,
+    mut segments: Query<(&Player, &mut Visibility), With<SnakeSegment>>,
+    demo: Option<Res<Demo>>,
+    mut game_state: ResMut<NextState<GameState>>,
+) {
+    timer.tick(time.delta());
+
+    let visible = ((timer.elapsed_secs() / DEATH_BLINK_SECONDS) as u32).is_multiple_of(2);
+    for (player, mut visibility) in &mut segments {
+        if simulation.snakes()[player.0].death().is_none() {
+            continue;
+        }
+        *visibility = if visible {
+            Visibility::Inherited
+        } else {
+            Visibility::Hidden
+        };
+    }
+
+    if timer.finished() {
+        game_state.set(end_screen(demo.is_some()));
+    }
+}
+
+// A demo goes back to the splash screen instead of summing the game up
+fn end_screen(demo: bool) -> GameState {
+    if demo {
+        GameState::Splash
+    } else {
+        GameState::GameOver
+    }
+}
+
+// Area of the window left to the arena once the score bar is laid out
+fn board_size(window: &Window) -> Vec2 {
+    Vec2::new(window.width(), window.height() - HUD_HEIGHT)
+}
+
 /*
 The sizing logic goes like so: if something has a width of 1 in a grid of 40,
-and the window is 400px across, then it should have a width of 10.
+and the board is 400px across, then it should have a width of 10.
  */
-fn size_scaling(windows: Query<&Window>, mut q: Query<(&Size, &mut Transform)>) {
-    let window = windows.single();
+fn size_scaling(
+    windows: Query<&Window>,
+    simulation: Res<Simulation>,
+    mut q: Query<(&Size, &mut Transform)>,
+) {
+    let board = board_size(windows.single());
+    let arena = simulation.arena();
 
     for (sprite_size, mut transform) in q.iter_mut() {
         transform.scale = Vec3::new(
-            sprite_size.width / ARENA_WIDTH as f32 * window.width() as f32,
-            sprite_size.height / ARENA_HEIGHT as f32 * window.height() as f32,
+            sprite_size.width / arena.width as f32 * board.x,
+            sprite_size.height / arena.height as f32 * board.y,
             1.,
         )
     }
 }
-fn position_translation(windows: Query<&Window>, mut q: Query<(&Position, &mut Transform)>) {
+fn position_translation(
+    windows: Query<&Window>,
+    simulation: Res<Simulation>,
+    mut q: Query<(&Position, &mut Transform)>,
+) {
     fn convert(pos: f32, bound_window: f32, bound_game: f32) -> f32 {
         let tile_size = bound_window / bound_game;
 
         pos / bound_game * bound_window - (bound_window / 2.) + (tile_size / 2.)
     }
 
-    let window = windows.single();
+    let board = board_size(windows.single());
+    let arena = simulation.arena();
     for (pos, mut transform) in q.iter_mut() {
         transform.translation = Vec3::new(
-            convert(pos.x as f32, window.width() as f32, ARENA_WIDTH as f32),
-            convert(pos.y as f32, window.height() as f32, ARENA_HEIGHT as f32),
+            convert(pos.x as f32, board.x, arena.width as f32),
+            // The board sits below the score bar, not in the middle of the window
+            convert(pos.y as f32, board.y, arena.height as f32) - HUD_HEIGHT / 2.,
             0.0,
         );
     }
 }
+
+/// Cell under a point of the window, such as the cursor: the inverse of `position_translation`.
+pub fn window_to_grid(window: &Window, arena: Arena, point: Vec2) -> Option<Position> {
+    fn convert(world: f32, bound_window: f32, bound_game: f32) -> i32 {
+        ((world + bound_window / 2.) / bound_window * bound_game).floor() as i32
+    }
+
+    let board = board_size(window);
+    // Window coordinates go down from the top left corner, world ones up from the center
+    let world = Vec2::new(
+        point.x - window.width() / 2.,
+        window.height() / 2. - point.y,
+    );
+    let position = Position {
+        x: convert(world.x, board.x, arena.width as f32),
+        y: convert(world.y + HUD_HEIGHT / 2., board.y, arena.height as f32),
+    };
+    arena.contains(position).then_some(position)
+}
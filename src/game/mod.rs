@@ -1,4 +1,9 @@
 pub mod food;
+#[allow(clippy::module_inception)]
 pub mod game;
+pub mod music;
+pub mod obstacle;
+pub mod replay;
 pub mod snake;
 pub mod sound;
+pub mod synth;
@@ -0,0 +1,208 @@
+use bevy::{audio, prelude::*};
+
+use super::{
+    snake::SnakeDirectionTimer,
+    sound::{AudioChannel, Fade},
+    synth::{Envelope, Note, Synth, Waveform},
+};
+use crate::{demo::Demo, utils::GameState};
+
+const CROSSFADE_SECONDS: f32 = 1.5;
+// The game track changes tempo while playing, overlapping both for long sounds muddy
+const TEMPO_CROSSFADE_SECONDS: f32 = 0.3;
+
+// Tick length of a new game, before any speed tier
+const GAME_STEP_SECONDS: f32 = 0.2;
+const MENU_STEP_SECONDS: f32 = 0.3;
+const GAME_OVER_STEP_SECONDS: f32 = 0.5;
+
+pub fn music_plugin(app: &mut App) {
+    app.add_systems(Update, (switch_track, crossfade).chain());
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Track {
+    Menu,
+    /// One step of the melody per snake tick, so the music follows the game speed.
+    Game {
+        step: f32,
+    },
+    GameOver,
+}
+
+// Frequency of a MIDI note number, 69 being the A at 440Hz
+fn midi(note: u8) -> f32 {
+    440. * 2f32.powf((note as f32 - 69.) / 12.)
+}
+
+impl Track {
+    fn is_same_tune(self, other: Track) -> bool {
+        std::mem::discriminant(&self) == std::mem::discriminant(&other)
+    }
+
+    fn synth(self) -> Synth {
+        match self {
+            Track::Menu => {
+                let step = MENU_STEP_SECONDS;
+                let pad = Envelope::new(0.3, 0.2, 0.8, 0.4);
+                let chords: [[u8; 3]; 4] = [[57, 60, 64], [53, 57, 60], [48, 52, 55], [55, 59, 62]];
+                let notes = chords.iter().enumerate().flat_map(|(bar, chord)| {
+                    let start = bar as f32 * 4. * step;
+                    let arpeggio =
+                        [0, 1, 2, 1]
+                            .into_iter()
+                            .enumerate()
+                            .map(move |(beat, index)| {
+                                Note::new(Waveform::Triangle, midi(chord[index]), step * 0.5)
+                                    .at(start + beat as f32 * step)
+                                    .gain(0.15)
+                            });
+                    let root = Note::new(Waveform::Sine, midi(chord[0] - 12), 4. * step - 0.4)
+                        .at(start)
+                        .envelope(pad)
+                        .gain(0.12);
+                    arpeggio.chain([root])
+                });
+                Synth::new(notes).with_length(16. * step)
+            }
+            Track::Game { step } => {
+                let bass = [45, 45, 41, 43]
+                    .into_iter()
+                    .enumerate()
+                    .map(|(beat, note)| {
+                        Note::new(Waveform::Square, midi(note), step * 1.5)
+                            .at(beat as f32 * 4. * step)
+                            .envelope(Envelope::new(0.005, step, 0.5, step * 0.4))
+                            .gain(0.1)
+                    });
+                let melody = [
+                    Some(69),
+                    None,
+                    Some(72),
+                    Some(74),
+                    Some(76),
+                    None,
+                    Some(74),
+                    Some(72),
+                    Some(69),
+                    None,
+                    Some(67),
+                    Some(69),
+                    Some(72),
+                    None,
+                    Some(71),
+                    None,
+                ];
+                let lead = melody.into_iter().enumerate().filter_map(|(index, note)| {
+                    Some(
+                        Note::new(Waveform::Triangle, midi(note?), step * 0.6)
+                            .at(index as f32 * step)
+                            .envelope(Envelope::new(0.005, 0.05, 0.7, step * 0.3))
+                            .gain(0.16),
+                    )
+                });
+                let hats = (0..8).map(|beat| {
+                    Note::new(Waveform::Noise, 8000., 0.005)
+                        .at(beat as f32 * 2. * step)
+                        .envelope(Envelope::new(0.001, 0., 1., 0.03))
+                        .gain(0.05)
+                });
+                Synth::new(bass.chain(lead).chain(hats)).with_length(16. * step)
+            }
+            Track::GameOver => {
+                let step = GAME_OVER_STEP_SECONDS;
+                let pad = Envelope::new(0.2, 0.3, 0.6, 0.4);
+                let melody = [64, 62, 60, 57]
+                    .into_iter()
+                    .enumerate()
+                    .map(|(index, note)| {
+                        Note::new(Waveform::Sine, midi(note), 2. * step - 0.4)
+                            .at(index as f32 * 2. * step)
+                            .envelope(pad)
+                            .gain(0.2)
+                    });
+                let drone = Note::new(Waveform::Triangle, midi(45), 8. * step - 0.4)
+                    .envelope(pad)
+                    .gain(0.1);
+                Synth::new(melody.chain([drone])).with_length(8. * step)
+            }
+        }
+    }
+}
+
+#[derive(Component)]
+struct Music {
+    track: Track,
+    fading_out: bool,
+    fade_seconds: f32,
+}
+
+// Starts the track matching the current screen, fading out the previous one
+fn switch_track(
+    mut commands: Commands,
+    mut synths: ResMut<Assets<Synth>>,
+    game_state: Res<State<GameState>>,
+    timer: Option<Res<SnakeDirectionTimer>>,
+    demo: Option<Res<Demo>>,
+    mut playing: Query<&mut Music>,
+) {
+    let wanted = match game_state.get() {
+        GameState::Splash | GameState::Menu | GameState::Editor => Track::Menu,
+        // Demos play along the splash screen
+        GameState::Game if demo.is_some() => Track::Menu,
+        GameState::Game => Track::Game {
+            step: timer.map_or(GAME_STEP_SECONDS, |timer| timer.0.duration().as_secs_f32()),
+        },
+        GameState::GameOver => Track::GameOver,
+    };
+    let current = playing
+        .iter()
+        .find(|music| !music.fading_out)
+        .map(|music| music.track);
+    if current == Some(wanted) {
+        return;
+    }
+
+    let fade_seconds = match current {
+        Some(current) if current.is_same_tune(wanted) => TEMPO_CROSSFADE_SECONDS,
+        _ => CROSSFADE_SECONDS,
+    };
+    for mut music in &mut playing {
+        if !music.fading_out {
+            music.fading_out = true;
+            music.fade_seconds = fade_seconds;
+        }
+    }
+    commands.spawn((
+        AudioSourceBundle {
+            source: synths.add(wanted.synth()),
+            // Silent until the first fade step, the mixer sets the actual volume
+            settings: PlaybackSettings::LOOP.with_volume(audio::Volume::new(0.)),
+        },
+        AudioChannel::Music,
+        Fade(0.),
+        Music {
+            track: wanted,
+            fading_out: false,
+            fade_seconds,
+        },
+    ));
+}
+
+fn crossfade(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut playing: Query<(Entity, &Music, &mut Fade)>,
+) {
+    for (entity, music, mut fade) in &mut playing {
+        let step = time.delta_seconds() / music.fade_seconds;
+        if music.fading_out {
+            fade.0 = (fade.0 - step).max(0.);
+            if fade.0 == 0. {
+                commands.entity(entity).despawn();
+            }
+        } else if fade.0 < 1. {
+            fade.0 = (fade.0 + step).min(1.);
+        }
+    }
+}
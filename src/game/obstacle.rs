@@ -0,0 +1,30 @@
+use bevy::prelude::*;
+
+use crate::utils::Size;
+
+use super::{game::OnGameScreen, snake::Simulation};
+
+pub const OBSTACLE_COLOR: Color = Color::srgb(0.55, 0.35, 0.2);
+
+/// A cell of the level the snake can't go through.
+#[derive(Component)]
+pub struct Obstacle;
+
+/// Obstacles never move, they are spawned once when the game starts.
+pub fn spawn_obstacles(mut commands: Commands, simulation: Res<Simulation>) {
+    for position in simulation.obstacles() {
+        commands.spawn((
+            SpriteBundle {
+                sprite: Sprite {
+                    color: OBSTACLE_COLOR,
+                    ..default()
+                },
+                ..default()
+            },
+            OnGameScreen,
+            Obstacle,
+            *position,
+            Size::square(1.0),
+        ));
+    }
+}
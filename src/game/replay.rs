@@ -0,0 +1,124 @@
+use std::io;
+
+use bevy::prelude::*;
+
+use crate::{
+    controls::{Action, ActionInput},
+    game::snake::Simulation,
+    sim::{level::Level, replay::Replay},
+    utils::{Direction, GameMode, GameState},
+};
+
+const PLAYBACK_SPEEDS: [f32; 4] = [1., 2., 4., 8.];
+
+/// Inputs of the game being played, saved when it ends.
+#[derive(Resource, Default, Deref, DerefMut)]
+pub struct ReplayRecorder(pub Replay);
+
+/// Present while a saved replay drives the snake instead of the keyboard.
+#[derive(Resource)]
+pub struct Playback {
+    replay: Replay,
+    cursor: usize,
+    paused: bool,
+    speed: usize,
+    frame_step: bool,
+}
+
+impl Playback {
+    pub fn new(replay: Replay) -> Self {
+        Self {
+            replay,
+            cursor: 0,
+            paused: false,
+            speed: 0,
+            frame_step: false,
+        }
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.replay.seed
+    }
+
+    pub fn mode(&self) -> GameMode {
+        self.replay.mode
+    }
+
+    pub fn load_level(&self) -> io::Result<Level> {
+        self.replay.load_level()
+    }
+
+    pub fn players(&self) -> usize {
+        self.replay.players()
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    pub fn speed(&self) -> f32 {
+        PLAYBACK_SPEEDS[self.speed]
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.cursor >= self.replay.len()
+    }
+
+    /// The recorded direction of every snake for the next tick.
+    pub fn next_inputs(&mut self) -> Option<Vec<Direction>> {
+        let inputs = self.replay.inputs_at(self.cursor);
+        self.cursor += 1;
+        inputs
+    }
+
+    /// Whether a single tick was requested while paused.
+    pub fn take_frame_step(&mut self) -> bool {
+        std::mem::take(&mut self.frame_step)
+    }
+}
+
+#[derive(Component)]
+pub struct PlaybackText;
+
+// Pause stops and resumes, Confirm changes the speed and Right steps a single tick while paused
+pub fn playback_controls(
+    actions: Res<ActionInput>,
+    mut playback: ResMut<Playback>,
+    simulation: Res<Simulation>,
+    mut game_state: ResMut<NextState<GameState>>,
+) {
+    if actions.just_pressed(Action::Pause) {
+        playback.paused = !playback.paused;
+    }
+    if actions.just_pressed(Action::Confirm) {
+        playback.speed = (playback.speed + 1) % PLAYBACK_SPEEDS.len();
+    }
+    if playback.paused && actions.just_pressed(Action::Right) {
+        playback.frame_step = true;
+    }
+    // A replay ending with the game still going has been cut short, there is nothing to sum up
+    if actions.just_pressed(Action::Back) || (playback.is_finished() && !simulation.is_over()) {
+        game_state.set(GameState::Menu);
+    }
+}
+
+pub fn update_playback_text(
+    playback: Res<Playback>,
+    mut texts: Query<&mut Text, With<PlaybackText>>,
+) {
+    if !playback.is_changed() {
+        return;
+    }
+
+    for mut text in &mut texts {
+        text.sections[0].value = if playback.is_paused() {
+            "Replay paused".to_string()
+        } else {
+            format!("Replay x{}", playback.speed())
+        };
+    }
+}
+
+pub fn remove_playback(mut commands: Commands) {
+    commands.remove_resource::<Playback>();
+}
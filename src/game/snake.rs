@@ -1,151 +1,316 @@
+use std::{collections::VecDeque, time::Duration};
+
 use bevy::{prelude::*, sprite::SpriteBundle, time::Timer};
 
 use crate::{
+    controls::{Action, ActionInput},
     game::food::Food,
-    game::game::GameOverEvent,
-    utils::{Direction, Position, Size, ARENA_HEIGHT, ARENA_WIDTH},
+    game::game::{GameOverEvent, GameRng, GameWonEvent},
+    net::NetSession,
+    sim::{
+        ai::{SnakeController, Strategy},
+        world::{Snake, SnakeWorld, StepOutcome},
+    },
+    utils::{Direction, Position, Size},
 };
 
 use super::{
-    game::{Hud, OnGameScreen, ScoreText},
-    sound::FoodEatenPitchEvent,
+    game::{OnGameScreen, ScoreText},
+    replay::{Playback, ReplayRecorder},
+    sound::{SoundEffect, SoundEffectEvent},
 };
 
 #[derive(Component)]
 pub struct SnakeSegment;
-#[derive(Default, Resource)]
+
+/// Entities drawing one snake, head first, kept on its head.
+#[derive(Component, Default)]
 pub struct SnakeSegments(pub Vec<Entity>);
-#[derive(Default, Resource)]
-pub struct LastTailPosition(Option<Position>);
+
+/// Index of the snake an entity draws, in the order of `SnakeWorld::snakes`.
+#[derive(Component, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Player(pub usize);
 
 #[derive(Resource)]
 pub struct SnakeDirectionTimer(pub Timer);
 
+/// The game rules, the rest of the plugin only mirrors this state on screen.
+#[derive(Resource, Default, Deref, DerefMut)]
+pub struct Simulation(pub SnakeWorld);
+
 #[derive(Component)]
-pub struct SnakeHead {
-    direction: Direction,
+pub struct SnakeHead;
+
+/// A computer player driving a snake instead of the keyboard.
+pub struct Cpu {
+    pub strategy: Strategy,
+    controller: Box<dyn SnakeController>,
 }
-#[derive(Component)]
-pub struct UserInput {
-    direction: Direction,
+
+impl Cpu {
+    pub fn new(strategy: Strategy) -> Self {
+        Self {
+            strategy,
+            controller: strategy.controller(),
+        }
+    }
+}
+
+/// Computer players of the game by snake, `None` for the snakes played by hand.
+#[derive(Resource, Default)]
+pub struct SnakeControllers(pub Vec<Option<Cpu>>);
+
+impl SnakeControllers {
+    pub fn is_cpu(&self, player: usize) -> bool {
+        self.0.get(player).is_some_and(Option::is_some)
+    }
+
+    /// How a player is called in the score bar and the game summary.
+    ///
+    /// Computer players are named after their strategy when they play among themselves.
+    pub fn label(&self, player: usize) -> String {
+        match self.0.get(player) {
+            Some(Some(cpu)) if self.0.iter().all(Option::is_some) => {
+                cpu.strategy.name().to_string()
+            }
+            Some(Some(_)) => "CPU".to_string(),
+            _ => format!("P{}", player + 1),
+        }
+    }
 }
 
-#[derive(Event)]
-pub struct GrowthEvent;
-#[derive(Event)]
-pub struct ShrinkEvent;
+/// Turns pressed ahead of the ticks that apply them, so quick double turns are not lost.
+#[derive(Component, Default)]
+pub struct InputQueue(VecDeque<Direction>);
+
+const MAX_QUEUED_TURNS: usize = 3;
 
-const SNAKE_HEAD_COLOR: Color = Color::srgb(0.7, 0.7, 0.7);
-const SNAKE_SEGMENT_COLOR: Color = Color::srgb(0.3, 0.3, 0.3);
+impl InputQueue {
+    /// Queues a turn, unless it would do nothing or reverse the direction left by the earlier turns.
+    pub fn push(&mut self, direction: Direction, current: Direction) -> bool {
+        let planned = self.0.back().copied().unwrap_or(current);
+        if self.0.len() >= MAX_QUEUED_TURNS
+            || direction == planned
+            || direction == planned.opposite()
+        {
+            return false;
+        }
+        self.0.push_back(direction);
+        true
+    }
 
-pub fn spawn_snake(mut commands: Commands, mut segments: ResMut<SnakeSegments>) {
-    *segments = SnakeSegments(vec![
-        commands
+    pub fn pop(&mut self) -> Option<Direction> {
+        self.0.pop_front()
+    }
+}
+
+pub const SNAKE_HEAD_COLOR: Color = Color::srgb(0.7, 0.7, 0.7);
+pub const SNAKE_SEGMENT_COLOR: Color = Color::srgb(0.3, 0.3, 0.3);
+const RIVAL_HEAD_COLOR: Color = Color::srgb(0.45, 0.8, 0.45);
+const RIVAL_SEGMENT_COLOR: Color = Color::srgb(0.15, 0.4, 0.15);
+
+/// Head and body colors of a player's snake, the second player is green.
+pub fn snake_colors(player: usize) -> (Color, Color) {
+    match player {
+        0 => (SNAKE_HEAD_COLOR, SNAKE_SEGMENT_COLOR),
+        _ => (RIVAL_HEAD_COLOR, RIVAL_SEGMENT_COLOR),
+    }
+}
+
+pub fn spawn_snake(mut commands: Commands, simulation: Res<Simulation>) {
+    for (index, snake) in simulation.snakes().iter().enumerate() {
+        let player = Player(index);
+        let head = commands
             .spawn((
                 SpriteBundle {
                     sprite: Sprite {
-                        color: SNAKE_HEAD_COLOR,
+                        color: snake_colors(index).0,
                         ..default()
                     },
                     ..default()
                 },
                 OnGameScreen,
             ))
-            .insert(SnakeHead {
-                direction: Direction::Up,
-            })
-            .insert(UserInput {
-                direction: Direction::Up,
-            })
+            .insert(SnakeHead)
+            .insert(InputQueue::default())
             .insert(SnakeSegment)
-            .insert(Position { x: 3, y: 3 })
+            .insert(player)
+            .insert(snake.head())
             .insert(Size::square(0.8))
-            .id(),
-        spawn_segment(commands, Position { x: 3, y: 2 }),
-    ]);
+            .id();
+
+        let mut segments = SnakeSegments(vec![head]);
+        for position in &snake.segments()[1..] {
+            segments
+                .0
+                .push(spawn_segment(&mut commands, player, *position));
+        }
+        commands.entity(head).insert(segments);
+    }
 }
 
 pub fn snake_movement_input(
-    keyboard_input: Res<ButtonInput<KeyCode>>,
-    mut user_input: Query<&mut UserInput>,
+    actions: Res<ActionInput>,
+    simulation: Res<Simulation>,
+    controllers: Res<SnakeControllers>,
+    net: Option<Res<NetSession>>,
+    mut input_queues: Query<(&Player, &mut InputQueue)>,
 ) {
-    if let Some(mut user_input) = user_input.iter_mut().next() {
-        if keyboard_input.pressed(KeyCode::ArrowLeft) {
-            user_input.direction = Direction::Left;
-        } else if keyboard_input.pressed(KeyCode::ArrowRight) {
-            user_input.direction = Direction::Right;
-        } else if keyboard_input.pressed(KeyCode::ArrowDown) {
-            user_input.direction = Direction::Down;
-        } else if keyboard_input.pressed(KeyCode::ArrowUp) {
-            user_input.direction = Direction::Up;
+    // Alone at the keyboard, a player uses every key and gamepad, in versus each player has their own
+    let is_local = |player: usize| {
+        !controllers.is_cpu(player) && net.as_ref().is_none_or(|net| net.local_player() == player)
+    };
+    let versus = (0..simulation.snakes().len())
+        .filter(|player| is_local(*player))
+        .count()
+        > 1;
+    for (player, mut input_queue) in &mut input_queues {
+        if !is_local(player.0) {
+            continue;
+        }
+        let pressed = if versus {
+            actions.iter_just_pressed_by(player.0).collect::<Vec<_>>()
+        } else {
+            actions.iter_just_pressed().collect()
+        };
+        let current = simulation.snakes()[player.0].direction();
+        for direction in pressed.into_iter().filter_map(Action::direction) {
+            input_queue.push(direction, current);
         }
     }
 }
 
 pub fn snake_movement(
-    segments: ResMut<SnakeSegments>,
-    mut heads: Query<(Entity, &mut SnakeHead, &UserInput)>,
-    mut positions: Query<&mut Position>,
+    mut simulation: ResMut<Simulation>,
+    mut rng: ResMut<GameRng>,
+    mut input_queues: Query<(&Player, &mut InputQueue)>,
+    mut controllers: ResMut<SnakeControllers>,
+    mut recorder: ResMut<ReplayRecorder>,
+    mut playback: Option<ResMut<Playback>>,
+    mut net: Option<ResMut<NetSession>>,
     mut game_over_writer: EventWriter<GameOverEvent>,
+    mut game_won_writer: EventWriter<GameWonEvent>,
+    mut sound_writer: EventWriter<SoundEffectEvent>,
     time: Res<Time>,
     mut timer: ResMut<SnakeDirectionTimer>,
-    mut last_tail_position: ResMut<LastTailPosition>,
 ) {
-    if timer.0.tick(time.delta()).just_finished() {
-        if let Some((head_entity, mut head, user_input)) = heads.iter_mut().next() {
-            if user_input.direction != head.direction.opposite() {
-                head.direction = user_input.direction;
+    let ticks = match playback.as_deref_mut() {
+        Some(playback) if playback.is_paused() => playback.take_frame_step() as u32,
+        Some(playback) => timer
+            .0
+            .tick(time.delta().mul_f32(playback.speed()))
+            .times_finished_this_tick(),
+        None => {
+            let ticks = timer.0.tick(time.delta()).times_finished_this_tick();
+            // Ticks waiting for the other player's inputs are run as soon as they arrive
+            match net.as_deref_mut() {
+                Some(net) => net.add_ticks(ticks),
+                None => ticks,
             }
-            let segment_positions = segments
-                .0
-                .iter()
-                .map(|e| *positions.get_mut(*e).unwrap())
-                .collect::<Vec<Position>>();
-            *last_tail_position = LastTailPosition(Some(*segment_positions.last().unwrap()));
-            let mut head_pos = positions.get_mut(head_entity).unwrap();
-
-            match &head.direction {
-                Direction::Left => {
-                    head_pos.x -= 1;
-                }
-                Direction::Right => {
-                    head_pos.x += 1;
+        }
+    };
+
+    // Only the turns of the players at this machine are heard, not computer, remote nor replayed ones
+    let local_players = (0..simulation.snakes().len())
+        .map(|player| {
+            playback.is_none()
+                && !controllers.is_cpu(player)
+                && net.as_ref().is_none_or(|net| net.local_player() == player)
+        })
+        .collect::<Vec<_>>();
+
+    for _ in 0..ticks {
+        let previous_directions = simulation
+            .snakes()
+            .iter()
+            .map(Snake::direction)
+            .collect::<Vec<_>>();
+        let inputs = match (playback.as_deref_mut(), net.as_deref_mut()) {
+            (Some(playback), _) => playback.next_inputs(),
+            (None, Some(net)) => {
+                if net.needs_local_input() {
+                    let turn = input_queues
+                        .iter_mut()
+                        .find(|(player, _)| player.0 == net.local_player())
+                        .and_then(|(_, mut input_queue)| input_queue.pop());
+                    net.push_local_input(turn);
                 }
-                Direction::Up => {
-                    head_pos.y += 1;
+                net.next_inputs()
+            }
+            (None, None) => {
+                // Without a queued turn a snake keeps going
+                let mut inputs = previous_directions.clone();
+                for (player, mut input_queue) in &mut input_queues {
+                    if let Some(direction) = input_queue.pop() {
+                        inputs[player.0] = direction;
+                    }
                 }
-                Direction::Down => {
-                    head_pos.y -= 1;
+                for (player, cpu) in controllers.0.iter_mut().enumerate() {
+                    if let Some(cpu) = cpu {
+                        inputs[player] = cpu.controller.next_direction(&simulation, player);
+                    }
                 }
+                Some(inputs)
             }
+        };
+        let Some(inputs) = inputs else {
+            return;
+        };
+        for (player_inputs, direction) in recorder.inputs.iter_mut().zip(&inputs) {
+            player_inputs.push(*direction);
+        }
 
-            if head_pos.x < 0
-                || head_pos.y < 0
-                || head_pos.x as u32 >= ARENA_WIDTH
-                || head_pos.y as u32 >= ARENA_HEIGHT - 1
-            {
-                game_over_writer.send(GameOverEvent);
-            }
+        let outcomes = simulation.step_all(&inputs, &mut **rng);
+        if let Some(net) = net.as_deref_mut() {
+            net.record_checksum(simulation.checksum());
+        }
+        if simulation
+            .snakes()
+            .iter()
+            .zip(&previous_directions)
+            .zip(&local_players)
+            .any(|((snake, previous), local)| *local && snake.direction() != *previous)
+        {
+            sound_writer.send(SoundEffectEvent(SoundEffect::Turn));
+        }
 
-            if segment_positions.contains(&head_pos) {
-                game_over_writer.send(GameOverEvent);
+        for outcome in &outcomes {
+            if let StepOutcome::Ate(food) = outcome {
+                sound_writer.send(SoundEffectEvent(match food {
+                    Food::Grow => SoundEffect::Eat,
+                    Food::Shrink => SoundEffect::Shrink,
+                }));
+            }
+        }
+        let speed_duration = Duration::from_secs_f32(simulation.tick_duration());
+        if timer.0.duration() != speed_duration {
+            if speed_duration < timer.0.duration() {
+                sound_writer.send(SoundEffectEvent(SoundEffect::SpeedUp));
             }
+            *timer = SnakeDirectionTimer(Timer::new(speed_duration, TimerMode::Repeating));
+        }
 
-            segment_positions
-                .iter()
-                .zip(segments.0.iter().skip(1))
-                .for_each(|(pos, segment)| {
-                    *positions.get_mut(*segment).unwrap() = *pos;
-                });
+        if outcomes
+            .iter()
+            .any(|outcome| matches!(outcome, StepOutcome::Died(_)))
+        {
+            sound_writer.send(SoundEffectEvent(SoundEffect::Die));
+            game_over_writer.send(GameOverEvent);
+            return;
+        }
+        if outcomes.contains(&StepOutcome::Won) {
+            game_won_writer.send(GameWonEvent);
+            return;
         }
     }
 }
-fn spawn_segment(mut commands: Commands, position: Position) -> Entity {
+
+fn spawn_segment(commands: &mut Commands, player: Player, position: Position) -> Entity {
     commands
         .spawn((
             SpriteBundle {
                 sprite: Sprite {
-                    color: SNAKE_SEGMENT_COLOR,
+                    color: snake_colors(player.0).1,
                     ..default()
                 },
                 ..default()
@@ -153,81 +318,106 @@ fn spawn_segment(mut commands: Commands, position: Position) -> Entity {
             OnGameScreen,
         ))
         .insert(SnakeSegment)
+        .insert(player)
         .insert(position)
         .insert(Size::square(0.65))
         .id()
 }
 
-pub fn snake_growth(
-    commands: Commands,
-    last_tail_position: ResMut<LastTailPosition>,
-    mut segments: ResMut<SnakeSegments>,
-    mut growth_reader: EventReader<GrowthEvent>,
+/// Spawns or despawns tail segments so the entities match the simulated snakes.
+pub fn sync_snake_segments(
+    mut commands: Commands,
+    simulation: Res<Simulation>,
+    mut snakes: Query<(&Player, &mut SnakeSegments)>,
+    mut positions: Query<&mut Position, With<SnakeSegment>>,
 ) {
-    if growth_reader.read().next().is_some() {
-        segments
-            .0
-            .push(spawn_segment(commands, last_tail_position.0.unwrap()))
+    if !simulation.is_changed() {
+        return;
+    }
+
+    for (player, mut segments) in &mut snakes {
+        let simulated = simulation.snakes()[player.0].segments();
+        while segments.0.len() > simulated.len() {
+            let tail = segments.0.pop().unwrap();
+            commands.entity(tail).despawn();
+        }
+        for (index, position) in simulated.iter().enumerate() {
+            match segments.0.get(index) {
+                Some(entity) => {
+                    if let Ok(mut segment_position) = positions.get_mut(*entity) {
+                        *segment_position = *position;
+                    }
+                }
+                None => {
+                    let entity = spawn_segment(&mut commands, *player, *position);
+                    segments.0.push(entity);
+                }
+            }
+        }
     }
 }
-pub fn snake_shrink(
-    mut commands: Commands,
-    mut segments: ResMut<SnakeSegments>,
-    mut shrink_reader: EventReader<ShrinkEvent>,
-) {
-    if shrink_reader.read().next().is_some() {
-        let length = segments.0.len();
-        let tail = segments.0.drain(length - 1..).next().unwrap();
-        commands.entity(tail).despawn();
+
+pub fn update_score_text(simulation: Res<Simulation>, mut texts: Query<(&ScoreText, &mut Text)>) {
+    if !simulation.is_changed() {
+        return;
+    }
+
+    for (score_text, mut text) in &mut texts {
+        text.sections[1].value = simulation.snakes()[score_text.0].score().to_string();
     }
 }
 
-pub fn snake_eating(
-    mut commands: Commands,
-    mut growth_writer: EventWriter<GrowthEvent>,
-    mut shrink_writer: EventWriter<ShrinkEvent>,
-    mut pitch_writer: EventWriter<FoodEatenPitchEvent>,
-    food_positions: Query<(Entity, &Position, &Food), With<Food>>,
-    head_positions: Query<&Position, With<SnakeHead>>,
-    mut texts: Query<&mut Text, With<ScoreText>>,
-    mut game_speed: ResMut<SnakeDirectionTimer>,
-    mut hud: ResMut<Hud>,
-) {
-    for head_pos in head_positions.iter() {
-        for (ent, food_pos, food) in food_positions.iter() {
-            if food_pos == head_pos {
-                commands.entity(ent).despawn();
+#[cfg(test)]
+mod tests {
+    use rand::{rngs::StdRng, SeedableRng};
 
-                pitch_writer.send(FoodEatenPitchEvent(*food));
+    use super::*;
+    use crate::utils::Arena;
 
-                match food {
-                    Food::Grow => {
-                        growth_writer.send(GrowthEvent);
-                        hud.score += 100;
-                    }
-                    Food::Shrink => {
-                        shrink_writer.send(ShrinkEvent);
-                    }
-                }
+    #[test]
+    fn the_queue_holds_a_few_turns_at_most() {
+        let mut queue = InputQueue::default();
+        assert!(queue.push(Direction::Left, Direction::Up));
+        assert!(queue.push(Direction::Up, Direction::Up));
+        assert!(queue.push(Direction::Right, Direction::Up));
+        assert!(!queue.push(Direction::Down, Direction::Up));
+        assert_eq!(queue.0.len(), MAX_QUEUED_TURNS);
+    }
 
-                for mut text in &mut texts {
-                    let speed_duration = match hud.score {
-                        0..=400 => 0.20,
-                        500..=900 => 0.18,
-                        1000..=1300 => 0.16,
-                        1400..=1600 => 0.14,
-                        1700..=2000 => 0.12,
-                        _ => 0.1,
-                    };
-
-                    *game_speed = SnakeDirectionTimer(Timer::from_seconds(
-                        speed_duration,
-                        TimerMode::Repeating,
-                    ));
-
-                    text.sections[1].value = hud.score.to_string();
-                }
-            }
+    #[test]
+    fn a_turn_that_changes_nothing_is_dropped() {
+        let mut queue = InputQueue::default();
+        assert!(!queue.push(Direction::Up, Direction::Up));
+        assert!(queue.push(Direction::Left, Direction::Up));
+        assert!(!queue.push(Direction::Left, Direction::Up));
+        assert_eq!(queue.pop(), Some(Direction::Left));
+        assert_eq!(queue.pop(), None);
+    }
+
+    #[test]
+    fn reversing_the_last_queued_turn_is_rejected() {
+        let mut queue = InputQueue::default();
+        assert!(!queue.push(Direction::Down, Direction::Up));
+        assert!(queue.push(Direction::Left, Direction::Up));
+        assert!(!queue.push(Direction::Right, Direction::Up));
+        assert_eq!(queue.0, [Direction::Left]);
+    }
+
+    #[test]
+    fn a_double_turn_pressed_within_one_tick_plays_over_two() {
+        let mut world = SnakeWorld::new(Arena::SMALL);
+        let mut rng = StdRng::seed_from_u64(1);
+        let mut queue = InputQueue::default();
+        // Down reverses the snake heading up, but not once it turned left
+        assert!(queue.push(Direction::Left, world.direction()));
+        assert!(queue.push(Direction::Down, world.direction()));
+
+        for _ in 0..2 {
+            let direction = queue.pop().unwrap_or(world.direction());
+            world.step(direction, &mut rng);
         }
+        assert!(!world.is_over());
+        assert_eq!(world.head(), Position { x: 2, y: 2 });
+        assert_eq!(world.direction(), Direction::Down);
     }
 }
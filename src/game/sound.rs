@@ -1,25 +1,192 @@
-use std::time::Duration;
+use std::collections::HashMap;
 
-use bevy::prelude::*;
+use bevy::{
+    audio::{self, AddAudioSource},
+    ecs::system::SystemParam,
+    prelude::*,
+};
 
-use super::food::Food;
+use super::synth::{Envelope, Note, Synth, Waveform};
+use crate::utils::{MusicVolume, SfxVolume, Volume};
+
+/// Highest step of the volume sliders.
+const MAX_VOLUME: u32 = 9;
+
+pub fn sound_plugin(app: &mut App) {
+    app.add_audio_source::<Synth>()
+        .add_event::<SoundEffectEvent>()
+        .add_systems(Startup, load_sound_effects)
+        .add_systems(
+            Update,
+            (
+                play_button_clicks,
+                play_sound_effects,
+                update_playing_volumes,
+            )
+                .chain(),
+        );
+}
+
+/// Which volume slider a playing sound follows.
+#[derive(Component, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AudioChannel {
+    Sfx,
+    Music,
+}
+
+/// Level of a playing sound on top of its channel volume, used to fade music in and out.
+#[derive(Component, Clone, Copy, Debug, PartialEq)]
+pub struct Fade(pub f32);
+
+/// Volume settings, every sound gets its level from here.
+#[derive(SystemParam)]
+pub struct Mixer<'w> {
+    master: Res<'w, Volume>,
+    sfx: Res<'w, SfxVolume>,
+    music: Res<'w, MusicVolume>,
+}
+
+impl Mixer<'_> {
+    pub fn volume(&self, channel: AudioChannel) -> audio::Volume {
+        let channel = match channel {
+            AudioChannel::Sfx => self.sfx.0,
+            AudioChannel::Music => self.music.0,
+        };
+        let level = |step: u32| step.min(MAX_VOLUME) as f32 / MAX_VOLUME as f32;
+        audio::Volume::new(level(self.master.0) * level(channel))
+    }
+
+    /// Playback settings for a one-shot sound on this channel.
+    pub fn one_shot(&self, channel: AudioChannel) -> PlaybackSettings {
+        PlaybackSettings::DESPAWN.with_volume(self.volume(channel))
+    }
+
+    pub fn is_changed(&self) -> bool {
+        self.master.is_changed() || self.sfx.is_changed() || self.music.is_changed()
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum SoundEffect {
+    Eat,
+    Shrink,
+    Turn,
+    Die,
+    SpeedUp,
+    Pause,
+    MenuClick,
+}
+
+impl SoundEffect {
+    const ALL: [SoundEffect; 7] = [
+        SoundEffect::Eat,
+        SoundEffect::Shrink,
+        SoundEffect::Turn,
+        SoundEffect::Die,
+        SoundEffect::SpeedUp,
+        SoundEffect::Pause,
+        SoundEffect::MenuClick,
+    ];
+
+    fn synth(self) -> Synth {
+        match self {
+            // Two quick rising blips
+            SoundEffect::Eat => Synth::new([
+                Note::new(Waveform::Square, 440., 0.05).gain(0.25),
+                Note::new(Waveform::Square, 660., 0.07).at(0.05).gain(0.25),
+            ]),
+            SoundEffect::Shrink => Synth::new([Note::new(Waveform::Triangle, 600., 0.15)
+                .sweep(200.)
+                .gain(0.5)]),
+            // Played on every turn, so barely there
+            SoundEffect::Turn => Synth::new([Note::new(Waveform::Noise, 6000., 0.01)
+                .envelope(Envelope::new(0.001, 0., 1., 0.02))
+                .gain(0.12)]),
+            SoundEffect::Die => Synth::new([
+                Note::new(Waveform::Sawtooth, 400., 0.45)
+                    .sweep(50.)
+                    .envelope(Envelope::new(0.01, 0.1, 0.7, 0.3))
+                    .gain(0.35),
+                Note::new(Waveform::Noise, 3000., 0.35)
+                    .sweep(200.)
+                    .envelope(Envelope::new(0.01, 0.2, 0.5, 0.3))
+                    .gain(0.25),
+            ]),
+            // A major arpeggio
+            SoundEffect::SpeedUp => Synth::new(
+                [523.25, 659.25, 783.99, 1046.5]
+                    .into_iter()
+                    .enumerate()
+                    .map(|(index, frequency)| {
+                        Note::new(Waveform::Square, frequency, 0.05)
+                            .at(index as f32 * 0.06)
+                            .gain(0.2)
+                    }),
+            ),
+            SoundEffect::Pause => Synth::new([
+                Note::new(Waveform::Sine, 660., 0.07),
+                Note::new(Waveform::Sine, 440., 0.1).at(0.08),
+            ]),
+            SoundEffect::MenuClick => Synth::new([Note::new(Waveform::Square, 1200., 0.015)
+                .envelope(Envelope::new(0.001, 0., 1., 0.03))
+                .gain(0.15)]),
+        }
+    }
+}
 
 #[derive(Event)]
-pub struct FoodEatenPitchEvent(pub Food);
+pub struct SoundEffectEvent(pub SoundEffect);
 
-pub fn play_food_eaten_pitch(
-    mut pitch_assets: ResMut<Assets<Pitch>>,
-    mut events: EventReader<FoodEatenPitchEvent>,
+// Every effect is generated once and replayed from its handle
+#[derive(Resource)]
+struct SoundEffects(HashMap<SoundEffect, Handle<Synth>>);
+
+fn load_sound_effects(mut commands: Commands, mut synths: ResMut<Assets<Synth>>) {
+    let effects = SoundEffect::ALL
+        .into_iter()
+        .map(|effect| (effect, synths.add(effect.synth())))
+        .collect();
+    commands.insert_resource(SoundEffects(effects));
+}
+
+fn play_sound_effects(
+    mut events: EventReader<SoundEffectEvent>,
+    effects: Res<SoundEffects>,
     mut commands: Commands,
+    mixer: Mixer,
 ) {
-    for e in events.read() {
-        let pitch = match e.0 {
-            Food::Grow => Pitch::new(120., Duration::from_millis(150)),
-            Food::Shrink => Pitch::new(500., Duration::from_millis(150)),
-        };
-        commands.spawn(PitchBundle {
-            source: pitch_assets.add(pitch),
-            settings: PlaybackSettings::DESPAWN,
-        });
+    for SoundEffectEvent(effect) in events.read() {
+        commands.spawn((
+            AudioSourceBundle {
+                source: effects.0[effect].clone(),
+                settings: mixer.one_shot(AudioChannel::Sfx),
+            },
+            AudioChannel::Sfx,
+        ));
+    }
+}
+
+fn play_button_clicks(
+    interaction_query: Query<&Interaction, (Changed<Interaction>, With<Button>)>,
+    mut sound_writer: EventWriter<SoundEffectEvent>,
+) {
+    for interaction in &interaction_query {
+        if *interaction == Interaction::Pressed {
+            sound_writer.send(SoundEffectEvent(SoundEffect::MenuClick));
+        }
+    }
+}
+
+// Moving a slider also applies to the sounds already playing
+fn update_playing_volumes(
+    mixer: Mixer,
+    sinks: Query<(Ref<AudioSink>, &AudioChannel, Option<Ref<Fade>>)>,
+) {
+    for (sink, channel, fade) in &sinks {
+        let faded = fade.as_ref().is_some_and(Ref::is_changed);
+        if mixer.is_changed() || sink.is_added() || faded {
+            let level = fade.map_or(1., |fade| fade.0);
+            sink.set_volume(mixer.volume(*channel).get() * level);
+        }
     }
 }
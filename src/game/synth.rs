@@ -0,0 +1,267 @@
+use std::{f32::consts::TAU, time::Duration};
+
+use bevy::{
+    audio::{Decodable, Source},
+    prelude::*,
+};
+
+pub const SAMPLE_RATE: u32 = 44_100;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Waveform {
+    Sine,
+    Square,
+    Triangle,
+    Sawtooth,
+    /// Random levels, picked again on every period so the frequency sets how rough it sounds.
+    Noise,
+}
+
+impl Waveform {
+    // `phase` goes from 0 to 1 over one period
+    fn sample(self, phase: f32, noise: f32) -> f32 {
+        match self {
+            Waveform::Sine => (phase * TAU).sin(),
+            Waveform::Square => {
+                if phase < 0.5 {
+                    1.
+                } else {
+                    -1.
+                }
+            }
+            Waveform::Triangle => 1. - 4. * (phase - 0.5).abs(),
+            Waveform::Sawtooth => 2. * phase - 1.,
+            Waveform::Noise => noise,
+        }
+    }
+}
+
+/// Loudness of a note over time: attack, decay and release in seconds, sustain as a level from 0 to 1.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Envelope {
+    pub attack: f32,
+    pub decay: f32,
+    pub sustain: f32,
+    pub release: f32,
+}
+
+impl Envelope {
+    /// Short and percussive, fits most sound effects.
+    pub const PLUCK: Envelope = Envelope::new(0.005, 0.05, 0.6, 0.05);
+
+    pub const fn new(attack: f32, decay: f32, sustain: f32, release: f32) -> Self {
+        Self {
+            attack,
+            decay,
+            sustain,
+            release,
+        }
+    }
+
+    // Level `time` seconds into a note held for `hold` seconds
+    fn level(&self, time: f32, hold: f32) -> f32 {
+        if time < hold {
+            return self.held_level(time);
+        }
+        let released = if self.release > 0. {
+            1. - (time - hold) / self.release
+        } else {
+            0.
+        };
+        self.held_level(hold) * released.max(0.)
+    }
+
+    fn held_level(&self, time: f32) -> f32 {
+        if time < self.attack {
+            time / self.attack
+        } else if time < self.attack + self.decay {
+            1. - (1. - self.sustain) * (time - self.attack) / self.decay
+        } else {
+            self.sustain
+        }
+    }
+}
+
+/// One oscillator playing for a while, optionally sweeping to another frequency.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Note {
+    pub waveform: Waveform,
+    pub frequency: f32,
+    /// Frequency reached when the note ends.
+    pub sweep_to: f32,
+    /// Seconds from the start of the sound.
+    pub start: f32,
+    /// Seconds before the release.
+    pub hold: f32,
+    pub envelope: Envelope,
+    pub gain: f32,
+}
+
+impl Note {
+    pub fn new(waveform: Waveform, frequency: f32, hold: f32) -> Self {
+        Self {
+            waveform,
+            frequency,
+            sweep_to: frequency,
+            start: 0.,
+            hold,
+            envelope: Envelope::PLUCK,
+            gain: 0.5,
+        }
+    }
+
+    pub fn sweep(self, sweep_to: f32) -> Self {
+        Self { sweep_to, ..self }
+    }
+
+    pub fn at(self, start: f32) -> Self {
+        Self { start, ..self }
+    }
+
+    pub fn envelope(self, envelope: Envelope) -> Self {
+        Self { envelope, ..self }
+    }
+
+    pub fn gain(self, gain: f32) -> Self {
+        Self { gain, ..self }
+    }
+
+    fn length(&self) -> f32 {
+        self.hold + self.envelope.release
+    }
+
+    fn end(&self) -> f32 {
+        self.start + self.length()
+    }
+
+    // Sweeps are exponential so they sound even across octaves
+    fn frequency_at(&self, time: f32) -> f32 {
+        let progress = (time / self.length()).clamp(0., 1.);
+        self.frequency * (self.sweep_to / self.frequency).powf(progress)
+    }
+}
+
+/// A sound made of notes mixed together, generated when played instead of loaded from a file.
+#[derive(Asset, TypePath, Clone, Debug, Default, PartialEq)]
+pub struct Synth {
+    pub notes: Vec<Note>,
+    length: Option<f32>,
+}
+
+impl Synth {
+    pub fn new(notes: impl IntoIterator<Item = Note>) -> Self {
+        Self {
+            notes: notes.into_iter().collect(),
+            length: None,
+        }
+    }
+
+    /// Cuts or pads the sound to exactly this many seconds, so it loops in time.
+    pub fn with_length(self, length: f32) -> Self {
+        Self {
+            length: Some(length),
+            ..self
+        }
+    }
+
+    pub fn duration(&self) -> f32 {
+        self.length
+            .unwrap_or_else(|| self.notes.iter().map(Note::end).fold(0., f32::max))
+    }
+}
+
+impl Decodable for Synth {
+    type DecoderItem = f32;
+    type Decoder = SynthDecoder;
+
+    fn decoder(&self) -> Self::Decoder {
+        SynthDecoder {
+            voices: self
+                .notes
+                .iter()
+                .map(|note| Voice {
+                    note: *note,
+                    phase: 0.,
+                    noise: 0.,
+                })
+                .collect(),
+            sample: 0,
+            length: (self.duration() * SAMPLE_RATE as f32).ceil() as u32,
+            noise_state: 0x9E37_79B9,
+        }
+    }
+}
+
+struct Voice {
+    note: Note,
+    phase: f32,
+    noise: f32,
+}
+
+/// Mono samples of a [`Synth`].
+pub struct SynthDecoder {
+    voices: Vec<Voice>,
+    sample: u32,
+    length: u32,
+    noise_state: u32,
+}
+
+// xorshift, good enough for noise and the same on every play
+fn next_noise(state: &mut u32) -> f32 {
+    *state ^= *state << 13;
+    *state ^= *state >> 17;
+    *state ^= *state << 5;
+    *state as f32 / u32::MAX as f32 * 2. - 1.
+}
+
+impl Iterator for SynthDecoder {
+    type Item = f32;
+
+    fn next(&mut self) -> Option<f32> {
+        if self.sample >= self.length {
+            return None;
+        }
+
+        let time = self.sample as f32 / SAMPLE_RATE as f32;
+        self.sample += 1;
+
+        let mut mix = 0.;
+        for voice in &mut self.voices {
+            let note = voice.note;
+            if time < note.start || time >= note.end() {
+                continue;
+            }
+
+            let local = time - note.start;
+            voice.phase += note.frequency_at(local) / SAMPLE_RATE as f32;
+            if voice.phase >= 1. {
+                voice.phase = voice.phase.fract();
+                voice.noise = next_noise(&mut self.noise_state);
+            }
+            mix += note.waveform.sample(voice.phase, voice.noise)
+                * note.envelope.level(local, note.hold)
+                * note.gain;
+        }
+        Some(mix.clamp(-1., 1.))
+    }
+}
+
+impl Source for SynthDecoder {
+    fn current_frame_len(&self) -> Option<usize> {
+        None
+    }
+
+    fn channels(&self) -> u16 {
+        1
+    }
+
+    fn sample_rate(&self) -> u32 {
+        SAMPLE_RATE
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        Some(Duration::from_secs_f32(
+            self.length as f32 / SAMPLE_RATE as f32,
+        ))
+    }
+}
@@ -0,0 +1,375 @@
+use bevy::{
+    input::keyboard::{Key, KeyboardInput},
+    prelude::*,
+};
+
+use crate::{
+    campaign::CampaignRun,
+    editor::TestPlay,
+    game::{
+        food::Food,
+        snake::{Simulation, SnakeControllers},
+    },
+    high_scores::{HighScores, PendingHighScore, INITIALS_LENGTH},
+    menu::{button_system, focus_navigation, release_focus_press, NORMAL_BUTTON},
+    net::{NetSession, NetStatus},
+    utils::{despawn_screen, GameState, TEXT_COLOR},
+};
+
+pub fn game_over_plugin(app: &mut App) {
+    app.add_systems(OnEnter(GameState::GameOver), game_over_setup)
+        .add_systems(
+            Update,
+            (game_over_action, button_system).run_if(in_state(GameState::GameOver)),
+        )
+        // Typing initials uses the same keys as the navigation
+        .add_systems(
+            Update,
+            (release_focus_press, focus_navigation).chain().run_if(
+                in_state(GameState::GameOver).and_then(not(resource_exists::<PendingHighScore>)),
+            ),
+        )
+        .add_systems(
+            Update,
+            enter_initials.run_if(
+                in_state(GameState::GameOver).and_then(resource_exists::<PendingHighScore>),
+            ),
+        )
+        .add_systems(
+            OnExit(GameState::GameOver),
+            (despawn_screen::<OnGameOverScreen>, save_pending_high_score),
+        );
+}
+
+#[derive(Component)]
+struct OnGameOverScreen;
+
+#[derive(Component)]
+struct InitialsText;
+#[derive(Component)]
+struct InitialsHintText;
+
+// All actions that can be triggered from a button click
+#[derive(Component)]
+enum GameOverButtonAction {
+    Retry,
+    NextLevel,
+    BackToEditor,
+    BackToMainMenu,
+}
+
+fn game_over_setup(
+    mut commands: Commands,
+    simulation: Res<Simulation>,
+    controllers: Res<SnakeControllers>,
+    pending_high_score: Option<Res<PendingHighScore>>,
+    campaign: Option<Res<CampaignRun>>,
+    test_play: Option<Res<TestPlay>>,
+    net: Option<Res<NetSession>>,
+) {
+    let button_style = Style {
+        width: Val::Px(180.0),
+        height: Val::Px(55.0),
+        margin: UiRect::all(Val::Px(10.0)),
+        justify_content: JustifyContent::Center,
+        align_items: AlignItems::Center,
+        ..default()
+    };
+    let button_text_style = TextStyle {
+        font_size: 30.0,
+        color: TEXT_COLOR,
+        ..default()
+    };
+    let summary_text_style = TextStyle {
+        font_size: 24.0,
+        color: TEXT_COLOR,
+        ..default()
+    };
+
+    // A campaign game only ends alive once its goal is reached
+    let level_completed = campaign.is_some() && simulation.death().is_none();
+    let versus = simulation.snakes().len() > 1;
+    // A network game can also end with the connection
+    let net_status = net
+        .as_ref()
+        .map(|net| net.status())
+        .filter(|status| *status != NetStatus::Playing);
+    let title = match &campaign {
+        Some(campaign) if level_completed && !campaign.has_next() => "Campaign done!".to_string(),
+        Some(_) if level_completed => "Level complete!".to_string(),
+        _ if net_status.is_some() => "Game stopped".to_string(),
+        _ if versus => match simulation.winner() {
+            Some(winner) if net.as_ref().is_some_and(|net| net.local_player() == winner) => {
+                "You win!".to_string()
+            }
+            Some(_) if net.is_some() => "You lose".to_string(),
+            Some(winner) if controllers.is_cpu(winner) => "CPU wins!".to_string(),
+            Some(winner) => format!("Player {} wins!", winner + 1),
+            None => "Draw".to_string(),
+        },
+        _ if simulation.is_won() => "You win!".to_string(),
+        _ => "Game over".to_string(),
+    };
+    let cause = match (simulation.death(), &campaign) {
+        (Some(cause), _) => cause.describe().to_string(),
+        (None, Some(campaign)) => format!("Goal reached: {}", campaign.goal.describe()),
+        (None, None) => "Filled the board".to_string(),
+    };
+    let next_level = level_completed && campaign.as_ref().is_some_and(|run| run.has_next());
+    let mut actions = vec![
+        if next_level {
+            (GameOverButtonAction::NextLevel, "Next level")
+        } else {
+            (GameOverButtonAction::Retry, "Retry")
+        },
+        if test_play.is_some() {
+            (GameOverButtonAction::BackToEditor, "Edit")
+        } else {
+            (GameOverButtonAction::BackToMainMenu, "Main menu")
+        },
+    ];
+    // The other player is gone or busy with the summary, a new network game starts from the lobby
+    if net.is_some() {
+        actions.remove(0);
+    }
+    let summary = if versus {
+        let mut summary = simulation
+            .snakes()
+            .iter()
+            .enumerate()
+            .map(|(player, snake)| {
+                let line = format!(
+                    "{}: {} points, length {}",
+                    controllers.label(player),
+                    snake.score(),
+                    snake.segments().len()
+                );
+                match snake.death() {
+                    Some(cause) => format!("{line}, {}", cause.describe().to_lowercase()),
+                    None => line,
+                }
+            })
+            .collect::<Vec<_>>();
+        summary.push(format!("Time: {:.1}s", simulation.elapsed()));
+        summary.extend(net_status.map(NetStatus::describe));
+        summary
+    } else {
+        vec![
+            format!("Score: {}", simulation.score()),
+            format!("Length: {}", simulation.segments().len()),
+            format!("Time: {:.1}s", simulation.elapsed()),
+            format!(
+                "Food eaten: {} grow, {} shrink",
+                simulation.food_eaten(Food::Grow),
+                simulation.food_eaten(Food::Shrink)
+            ),
+            cause,
+        ]
+    };
+
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    width: Val::Percent(100.0),
+                    height: Val::Percent(100.0),
+                    align_items: AlignItems::Center,
+                    justify_content: JustifyContent::Center,
+                    ..default()
+                },
+                ..default()
+            },
+            OnGameOverScreen,
+        ))
+        .with_children(|parent| {
+            parent
+                .spawn(NodeBundle {
+                    style: Style {
+                        flex_direction: FlexDirection::Column,
+                        align_items: AlignItems::Center,
+                        padding: UiRect::all(Val::Px(10.0)),
+                        ..default()
+                    },
+                    background_color: bevy::color::palettes::css::CRIMSON.into(),
+                    ..default()
+                })
+                .with_children(|parent| {
+                    parent.spawn(
+                        TextBundle::from_section(
+                            title,
+                            TextStyle {
+                                font_size: 60.0,
+                                color: TEXT_COLOR,
+                                ..default()
+                            },
+                        )
+                        .with_style(Style {
+                            margin: UiRect::all(Val::Px(20.0)),
+                            ..default()
+                        }),
+                    );
+                    for line in summary {
+                        parent.spawn(
+                            TextBundle::from_section(line, summary_text_style.clone()).with_style(
+                                Style {
+                                    margin: UiRect::all(Val::Px(4.0)),
+                                    ..default()
+                                },
+                            ),
+                        );
+                    }
+
+                    if pending_high_score.is_some() {
+                        parent.spawn((
+                            TextBundle::from_sections([
+                                TextSection::new("New high score! ", summary_text_style.clone()),
+                                TextSection::new(
+                                    initials_prompt(""),
+                                    TextStyle {
+                                        color: bevy::color::palettes::css::GOLD.into(),
+                                        ..summary_text_style.clone()
+                                    },
+                                ),
+                            ])
+                            .with_style(Style {
+                                margin: UiRect::top(Val::Px(10.0)),
+                                ..default()
+                            }),
+                            InitialsText,
+                        ));
+                        parent.spawn((
+                            TextBundle::from_section(
+                                "Type your initials, Enter to save",
+                                TextStyle {
+                                    font_size: 18.0,
+                                    ..summary_text_style.clone()
+                                },
+                            ),
+                            InitialsHintText,
+                        ));
+                    }
+
+                    parent
+                        .spawn(NodeBundle {
+                            style: Style {
+                                margin: UiRect::top(Val::Px(10.0)),
+                                ..default()
+                            },
+                            ..default()
+                        })
+                        .with_children(|parent| {
+                            for (action, text) in actions {
+                                parent
+                                    .spawn((
+                                        ButtonBundle {
+                                            style: button_style.clone(),
+                                            background_color: NORMAL_BUTTON.into(),
+                                            ..default()
+                                        },
+                                        action,
+                                    ))
+                                    .with_children(|parent| {
+                                        parent.spawn(TextBundle::from_section(
+                                            text,
+                                            button_text_style.clone(),
+                                        ));
+                                    });
+                            }
+                        });
+                });
+        });
+}
+
+fn game_over_action(
+    interaction_query: Query<
+        (&Interaction, &GameOverButtonAction),
+        (Changed<Interaction>, With<Button>),
+    >,
+    mut commands: Commands,
+    campaign: Option<Res<CampaignRun>>,
+    mut game_state: ResMut<NextState<GameState>>,
+) {
+    for (interaction, action) in &interaction_query {
+        if *interaction == Interaction::Pressed {
+            match action {
+                GameOverButtonAction::Retry => game_state.set(GameState::Game),
+                GameOverButtonAction::NextLevel => {
+                    let Some(campaign) = &campaign else {
+                        continue;
+                    };
+                    match campaign.next() {
+                        Ok(next) => {
+                            commands.insert_resource(next);
+                            game_state.set(GameState::Game);
+                        }
+                        Err(err) => warn!("Could not load the next level: {err}"),
+                    }
+                }
+                GameOverButtonAction::BackToEditor => game_state.set(GameState::Editor),
+                GameOverButtonAction::BackToMainMenu => game_state.set(GameState::Menu),
+            }
+        }
+    }
+}
+
+fn initials_prompt(initials: &str) -> String {
+    format!("{initials:_<INITIALS_LENGTH$}")
+}
+
+fn enter_initials(
+    mut commands: Commands,
+    mut keyboard_events: EventReader<KeyboardInput>,
+    mut pending: ResMut<PendingHighScore>,
+    mut high_scores: ResMut<HighScores>,
+    mut initials_texts: Query<&mut Text, With<InitialsText>>,
+    mut hint_texts: Query<&mut Text, (With<InitialsHintText>, Without<InitialsText>)>,
+) {
+    for event in keyboard_events.read() {
+        if !event.state.is_pressed() {
+            continue;
+        }
+
+        match &event.logical_key {
+            Key::Character(characters) => {
+                for c in characters.chars().filter(char::is_ascii_alphanumeric) {
+                    if pending.initials.len() < INITIALS_LENGTH {
+                        pending.initials.push(c.to_ascii_uppercase());
+                    }
+                }
+            }
+            Key::Backspace => {
+                pending.initials.pop();
+            }
+            Key::Enter => {
+                let rank = pending.commit(&mut high_scores);
+                commands.remove_resource::<PendingHighScore>();
+                for mut text in &mut hint_texts {
+                    text.sections[0].value = match rank {
+                        Some(rank) => format!("Saved at rank {}", rank + 1),
+                        None => "Saved".to_string(),
+                    };
+                }
+                return;
+            }
+            _ => {}
+        }
+    }
+
+    if pending.is_changed() {
+        for mut text in &mut initials_texts {
+            text.sections[1].value = initials_prompt(&pending.initials);
+        }
+    }
+}
+
+// Leaving the screen without confirming still keeps the record
+fn save_pending_high_score(
+    mut commands: Commands,
+    pending: Option<Res<PendingHighScore>>,
+    mut high_scores: ResMut<HighScores>,
+) {
+    if let Some(pending) = pending {
+        pending.commit(&mut high_scores);
+        commands.remove_resource::<PendingHighScore>();
+    }
+}
@@ -0,0 +1,215 @@
+use std::{collections::BTreeMap, fs, io, path::PathBuf};
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    sim::world::SnakeWorld,
+    storage::data_dir,
+    utils::{Arena, GameMode},
+};
+
+pub const MAX_HIGH_SCORES: usize = 10;
+pub const INITIALS_LENGTH: usize = 3;
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct HighScore {
+    pub initials: String,
+    pub score: u32,
+    pub length: usize,
+}
+
+/// Best runs, one table per kind of game so small boards don't compete with large ones.
+#[derive(Resource, Debug, Default, Serialize, Deserialize)]
+pub struct HighScores {
+    tables: BTreeMap<String, Vec<HighScore>>,
+}
+
+/// Name of the table a game with these rules and board goes to, levels having their own tables.
+pub fn score_bucket(mode: GameMode, arena: Arena, level: Option<&str>) -> String {
+    match level {
+        Some(level) => format!("{} {level}", mode.name()),
+        None => format!("{} {}x{}", mode.name(), arena.width, arena.height),
+    }
+}
+
+fn high_scores_path() -> PathBuf {
+    data_dir().join("high_scores.json")
+}
+
+impl HighScores {
+    /// Reads the saved tables, starting from empty ones when there are none yet.
+    pub fn load() -> Self {
+        let Ok(text) = fs::read_to_string(high_scores_path()) else {
+            return Self::default();
+        };
+        serde_json::from_str(&text).unwrap_or_else(|err| {
+            warn!("Ignoring unreadable high scores: {err}");
+            Self::default()
+        })
+    }
+
+    pub fn save(&self) -> io::Result<()> {
+        fs::create_dir_all(data_dir())?;
+        fs::write(high_scores_path(), serde_json::to_string_pretty(self)?)
+    }
+
+    pub fn buckets(&self) -> impl Iterator<Item = &String> {
+        self.tables.keys()
+    }
+
+    pub fn table(&self, bucket: &str) -> &[HighScore] {
+        self.tables.get(bucket).map_or(&[], Vec::as_slice)
+    }
+
+    pub fn qualifies(&self, bucket: &str, score: u32) -> bool {
+        let table = self.table(bucket);
+        score > 0
+            && (table.len() < MAX_HIGH_SCORES
+                || table.last().is_some_and(|last| score > last.score))
+    }
+
+    /// Adds the entry at its rank and returns that rank, if it made the table.
+    pub fn insert(&mut self, bucket: &str, entry: HighScore) -> Option<usize> {
+        let table = self.tables.entry(bucket.to_string()).or_default();
+        let rank = table.partition_point(|other| other.score >= entry.score);
+        if rank >= MAX_HIGH_SCORES {
+            return None;
+        }
+        table.insert(rank, entry);
+        table.truncate(MAX_HIGH_SCORES);
+        Some(rank)
+    }
+}
+
+/// A run that made the table, waiting for the player's initials.
+#[derive(Resource, Debug)]
+pub struct PendingHighScore {
+    pub bucket: String,
+    pub score: u32,
+    pub length: usize,
+    pub initials: String,
+}
+
+impl PendingHighScore {
+    pub fn new(bucket: String, world: &SnakeWorld) -> Self {
+        Self {
+            bucket,
+            score: world.score(),
+            length: world.segments().len(),
+            initials: String::new(),
+        }
+    }
+
+    /// Records the run in the tables and saves them.
+    pub fn commit(&self, high_scores: &mut HighScores) -> Option<usize> {
+        let initials = if self.initials.is_empty() {
+            "???".to_string()
+        } else {
+            self.initials.clone()
+        };
+        let rank = high_scores.insert(
+            &self.bucket,
+            HighScore {
+                initials,
+                score: self.score,
+                length: self.length,
+            },
+        );
+        if let Err(err) = high_scores.save() {
+            warn!("Could not save high scores: {err}");
+        }
+        rank
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(initials: &str, score: u32) -> HighScore {
+        HighScore {
+            initials: initials.to_string(),
+            score,
+            length: 2,
+        }
+    }
+
+    // A full table scoring 1000 down to 100
+    fn full_table(bucket: &str) -> HighScores {
+        let mut high_scores = HighScores::default();
+        for score in (1..=MAX_HIGH_SCORES as u32).map(|rank| rank * 100) {
+            high_scores.insert(bucket, entry("AAA", score));
+        }
+        high_scores
+    }
+
+    #[test]
+    fn only_the_best_ten_are_kept() {
+        let mut high_scores = full_table("Classic 10x10");
+        assert!(!high_scores.qualifies("Classic 10x10", 100));
+        assert_eq!(high_scores.insert("Classic 10x10", entry("LOW", 100)), None);
+        assert!(high_scores.qualifies("Classic 10x10", 150));
+        assert_eq!(
+            high_scores.insert("Classic 10x10", entry("NEW", 150)),
+            Some(9)
+        );
+
+        let table = high_scores.table("Classic 10x10");
+        assert_eq!(table.len(), MAX_HIGH_SCORES);
+        assert_eq!(table[0].score, 1000);
+        assert_eq!(table[9].initials, "NEW");
+    }
+
+    #[test]
+    fn an_empty_run_never_qualifies() {
+        assert!(!HighScores::default().qualifies("Classic 10x10", 0));
+        assert!(HighScores::default().qualifies("Classic 10x10", 100));
+    }
+
+    #[test]
+    fn ties_rank_below_the_earlier_run() {
+        let mut high_scores = HighScores::default();
+        high_scores.insert("Classic 10x10", entry("OLD", 500));
+        assert_eq!(
+            high_scores.insert("Classic 10x10", entry("NEW", 500)),
+            Some(1)
+        );
+        assert_eq!(
+            high_scores.insert("Classic 10x10", entry("TOP", 600)),
+            Some(0)
+        );
+
+        let initials = high_scores
+            .table("Classic 10x10")
+            .iter()
+            .map(|high_score| high_score.initials.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(initials, ["TOP", "OLD", "NEW"]);
+    }
+
+    #[test]
+    fn every_bucket_has_its_own_table() {
+        let small = score_bucket(GameMode::Classic, Arena::SMALL, None);
+        let large = score_bucket(GameMode::Classic, Arena::LARGE, None);
+        let wrap = score_bucket(GameMode::Wrap, Arena::SMALL, None);
+        let level = score_bucket(GameMode::Classic, Arena::SMALL, Some("garden"));
+        assert_eq!(
+            [&small, &large, &wrap, &level].map(String::as_str),
+            [
+                "Classic 10x10",
+                "Classic 24x24",
+                "Wrap 10x10",
+                "Classic garden"
+            ]
+        );
+
+        let mut high_scores = full_table(&small);
+        assert!(!high_scores.qualifies(&small, 100));
+        assert!(high_scores.qualifies(&large, 100));
+        high_scores.insert(&level, entry("LVL", 300));
+        assert_eq!(high_scores.table(&small).len(), MAX_HIGH_SCORES);
+        assert_eq!(high_scores.table(&level).len(), 1);
+        assert!(high_scores.table(&wrap).is_empty());
+    }
+}
@@ -1,4 +1,28 @@
+#![allow(clippy::type_complexity, clippy::too_many_arguments)]
+
+#[cfg(feature = "bevy")]
+pub mod campaign;
+#[cfg(feature = "bevy")]
+pub mod controls;
+#[cfg(feature = "bevy")]
+pub mod demo;
+#[cfg(feature = "bevy")]
+pub mod editor;
+#[cfg(feature = "bevy")]
 pub mod game;
+#[cfg(feature = "bevy")]
+pub mod game_over;
+#[cfg(feature = "bevy")]
+pub mod high_scores;
+#[cfg(feature = "bevy")]
 pub mod menu;
+#[cfg(feature = "bevy")]
+pub mod net;
+#[cfg(feature = "bevy")]
+pub mod settings;
+pub mod sim;
+#[cfg(feature = "bevy")]
 pub mod splash;
+pub mod storage;
+#[cfg(feature = "bevy")]
 pub mod utils;
@@ -1,27 +1,43 @@
 // use bevy::core::FixedTimestep;
-use bevy::{
-    prelude::*,
-    window::{PresentMode, WindowTheme},
-};
+use bevy::{prelude::*, window::PresentMode};
 use rusty_snake::{
-    game::game::game_plugin,
+    campaign::CampaignProgress,
+    controls::controls_plugin,
+    demo::demo_plugin,
+    editor::editor_plugin,
+    game::{game::game_plugin, music::music_plugin, sound::sound_plugin},
+    game_over::game_over_plugin,
+    high_scores::HighScores,
     menu::menu_plugin,
+    net::net_plugin,
+    settings::Settings,
     splash::splash_plugin,
-    utils::{GameState, Volume},
+    utils::{GameState, MusicVolume, Seed, SelectedLevel, SfxVolume, Volume},
 };
 
+const USAGE: &str = "usage: rusty_snake [--seed <u64>]";
+
 fn main() {
+    let seed = match seed_from_args() {
+        Ok(seed) => seed,
+        Err(err) => {
+            eprintln!("{err}\n{USAGE}");
+            std::process::exit(2);
+        }
+    };
+    let settings = Settings::load();
+
     App::new()
         .add_plugins(DefaultPlugins.set(WindowPlugin {
             primary_window: Some(Window {
                 title: "Rusty Snake".into(),
                 name: Some("bevy.app".into()),
-                resolution: (500., 500.).into(),
+                resolution: (settings.window_width, settings.window_height).into(),
                 present_mode: PresentMode::AutoVsync,
                 // Tells wasm to resize the window according to the available canvas
                 // Tells wasm not to override default event handling, like F5, Ctrl+R etc.
                 prevent_default_event_handling: false,
-                window_theme: Some(WindowTheme::Dark),
+                window_theme: settings.theme.window_theme(),
                 enabled_buttons: bevy::window::EnabledButtons {
                     maximize: false,
                     ..Default::default()
@@ -31,12 +47,50 @@ fn main() {
             ..default()
         }))
         .init_state::<GameState>()
-        .insert_resource(Volume(7))
+        .insert_resource(Volume(settings.volume))
+        .insert_resource(SfxVolume(settings.sfx_volume))
+        .insert_resource(MusicVolume(settings.music_volume))
+        .insert_resource(settings.mode)
+        .insert_resource(settings.players)
+        .insert_resource(settings.arena)
+        .insert_resource(SelectedLevel(settings.level.clone()))
+        .insert_resource(settings.controls.clone())
+        .insert_resource(settings.theme)
+        .insert_resource(Seed(seed.or(settings.seed)))
+        .insert_resource(settings)
+        .insert_resource(HighScores::load())
+        .insert_resource(CampaignProgress::load())
         .add_systems(Startup, setup)
-        .add_plugins((splash_plugin, game_plugin, menu_plugin))
+        .add_plugins((
+            controls_plugin,
+            splash_plugin,
+            demo_plugin,
+            sound_plugin,
+            music_plugin,
+            game_plugin,
+            game_over_plugin,
+            menu_plugin,
+            net_plugin,
+            editor_plugin,
+        ))
         .run();
 }
 
 fn setup(mut commands: Commands) {
     commands.spawn(Camera2dBundle::default());
 }
+
+/// Reads `--seed <u64>` from the command line to replay a known game.
+fn seed_from_args() -> Result<Option<u64>, String> {
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        if arg == "--seed" {
+            let value = args.next().ok_or("missing value for --seed")?;
+            return value
+                .parse()
+                .map(Some)
+                .map_err(|_| format!("invalid seed {value}"));
+        }
+    }
+    Ok(None)
+}
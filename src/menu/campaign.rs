@@ -0,0 +1,189 @@
+use bevy::prelude::*;
+
+use super::{MenuButtonAction, MenuState, NORMAL_BUTTON};
+use crate::{
+    campaign::{campaign_levels, CampaignProgress, CampaignRun},
+    utils::{despawn_screen, GameState, TEXT_COLOR},
+};
+
+pub(super) fn campaign_menu_plugin(app: &mut App) {
+    app.add_systems(OnEnter(MenuState::Campaign), campaign_menu_setup)
+        .add_systems(
+            Update,
+            campaign_level_button.run_if(in_state(MenuState::Campaign)),
+        )
+        .add_systems(
+            OnExit(MenuState::Campaign),
+            despawn_screen::<OnCampaignMenuScreen>,
+        );
+}
+
+#[derive(Component)]
+struct OnCampaignMenuScreen;
+
+const LOCKED_LEVEL: Color = Color::srgb(0.08, 0.08, 0.08);
+
+// Starts the campaign level at this index
+#[derive(Component)]
+struct CampaignLevelButton(usize);
+
+fn campaign_menu_setup(mut commands: Commands, progress: Res<CampaignProgress>) {
+    let level_button_style = Style {
+        width: Val::Px(90.0),
+        height: Val::Px(70.0),
+        margin: UiRect::all(Val::Px(6.0)),
+        flex_direction: FlexDirection::Column,
+        justify_content: JustifyContent::Center,
+        align_items: AlignItems::Center,
+        ..default()
+    };
+    let button_style = Style {
+        width: Val::Px(150.0),
+        height: Val::Px(55.0),
+        margin: UiRect::all(Val::Px(8.0)),
+        justify_content: JustifyContent::Center,
+        align_items: AlignItems::Center,
+        ..default()
+    };
+    let button_text_style = TextStyle {
+        font_size: 32.0,
+        color: TEXT_COLOR,
+        ..default()
+    };
+    let status_text_style = TextStyle {
+        font_size: 18.0,
+        color: TEXT_COLOR,
+        ..default()
+    };
+
+    let levels = campaign_levels();
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    width: Val::Percent(100.0),
+                    height: Val::Percent(100.0),
+                    align_items: AlignItems::Center,
+                    justify_content: JustifyContent::Center,
+                    ..default()
+                },
+                ..default()
+            },
+            OnCampaignMenuScreen,
+        ))
+        .with_children(|parent| {
+            parent
+                .spawn(NodeBundle {
+                    style: Style {
+                        flex_direction: FlexDirection::Column,
+                        align_items: AlignItems::Center,
+                        padding: UiRect::all(Val::Px(10.0)),
+                        ..default()
+                    },
+                    background_color: bevy::color::palettes::css::CRIMSON.into(),
+                    ..default()
+                })
+                .with_children(|parent| {
+                    parent.spawn(TextBundle::from_section(
+                        format!(
+                            "Campaign {} / {}",
+                            progress.completed_count(&levels),
+                            levels.len()
+                        ),
+                        button_text_style.clone(),
+                    ));
+
+                    // Four levels per row, locked ones can't be picked
+                    parent
+                        .spawn(NodeBundle {
+                            style: Style {
+                                width: Val::Px(4. * 102.),
+                                flex_wrap: FlexWrap::Wrap,
+                                justify_content: JustifyContent::Center,
+                                ..default()
+                            },
+                            ..default()
+                        })
+                        .with_children(|parent| {
+                            for (index, level) in levels.iter().enumerate() {
+                                let unlocked = progress.is_unlocked(&levels, index);
+                                let status = if progress.is_completed(level) {
+                                    "Done"
+                                } else if unlocked {
+                                    ""
+                                } else {
+                                    "Locked"
+                                };
+                                let mut entity = if unlocked {
+                                    parent.spawn((
+                                        ButtonBundle {
+                                            style: level_button_style.clone(),
+                                            background_color: NORMAL_BUTTON.into(),
+                                            ..default()
+                                        },
+                                        CampaignLevelButton(index),
+                                    ))
+                                } else {
+                                    parent.spawn(NodeBundle {
+                                        style: level_button_style.clone(),
+                                        background_color: LOCKED_LEVEL.into(),
+                                        ..default()
+                                    })
+                                };
+                                entity.with_children(|parent| {
+                                    parent.spawn(TextBundle::from_section(
+                                        (index + 1).to_string(),
+                                        button_text_style.clone(),
+                                    ));
+                                    parent.spawn(TextBundle::from_section(
+                                        status,
+                                        status_text_style.clone(),
+                                    ));
+                                });
+                            }
+                        });
+                    if levels.is_empty() {
+                        parent.spawn(TextBundle::from_section(
+                            "No campaign levels",
+                            status_text_style.clone(),
+                        ));
+                    }
+
+                    parent
+                        .spawn((
+                            ButtonBundle {
+                                style: button_style,
+                                background_color: NORMAL_BUTTON.into(),
+                                ..default()
+                            },
+                            MenuButtonAction::BackToMainMenu,
+                        ))
+                        .with_children(|parent| {
+                            parent.spawn(TextBundle::from_section("Back", button_text_style));
+                        });
+                });
+        });
+}
+
+fn campaign_level_button(
+    interaction_query: Query<
+        (&Interaction, &CampaignLevelButton),
+        (Changed<Interaction>, With<Button>),
+    >,
+    mut commands: Commands,
+    mut menu_state: ResMut<NextState<MenuState>>,
+    mut game_state: ResMut<NextState<GameState>>,
+) {
+    for (interaction, CampaignLevelButton(index)) in &interaction_query {
+        if *interaction == Interaction::Pressed {
+            match CampaignRun::start(campaign_levels(), *index) {
+                Ok(run) => {
+                    commands.insert_resource(run);
+                    game_state.set(GameState::Game);
+                    menu_state.set(MenuState::Disabled);
+                }
+                Err(err) => warn!("Could not load campaign level {}: {err}", index + 1),
+            }
+        }
+    }
+}
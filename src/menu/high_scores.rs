@@ -0,0 +1,226 @@
+use std::collections::BTreeSet;
+
+use bevy::prelude::*;
+
+use super::{MenuButtonAction, MenuState, NORMAL_BUTTON};
+use crate::{
+    high_scores::{score_bucket, HighScores, MAX_HIGH_SCORES},
+    utils::{despawn_screen, Arena, GameMode, SelectedLevel, TEXT_COLOR},
+};
+
+pub(super) fn high_scores_menu_plugin(app: &mut App) {
+    app.add_systems(OnEnter(MenuState::HighScores), high_scores_menu_setup)
+        .add_systems(
+            Update,
+            (high_scores_page_button, update_high_scores_table)
+                .chain()
+                .run_if(in_state(MenuState::HighScores)),
+        )
+        .add_systems(
+            OnExit(MenuState::HighScores),
+            despawn_screen::<OnHighScoresMenuScreen>,
+        );
+}
+
+#[derive(Component)]
+struct OnHighScoresMenuScreen;
+
+// Browses the high score tables, one kind of game at a time
+#[derive(Resource)]
+struct ShownHighScores {
+    buckets: Vec<String>,
+    index: usize,
+}
+
+#[derive(Component)]
+struct HighScoresPageButton(isize);
+
+#[derive(Component)]
+struct HighScoresBucketText;
+
+#[derive(Component)]
+struct HighScoreRow(usize);
+
+fn high_scores_menu_setup(
+    mut commands: Commands,
+    high_scores: Res<HighScores>,
+    mode: Res<GameMode>,
+    arena: Res<Arena>,
+    level: Res<SelectedLevel>,
+) {
+    let button_style = Style {
+        width: Val::Px(50.0),
+        height: Val::Px(40.0),
+        margin: UiRect::all(Val::Px(8.0)),
+        justify_content: JustifyContent::Center,
+        align_items: AlignItems::Center,
+        ..default()
+    };
+    let button_text_style = TextStyle {
+        font_size: 30.0,
+        color: TEXT_COLOR,
+        ..default()
+    };
+    let row_text_style = TextStyle {
+        font_size: 22.0,
+        color: TEXT_COLOR,
+        ..default()
+    };
+
+    // Start from the table of the mode and board currently picked
+    let current = score_bucket(*mode, *arena, level.0.as_deref());
+    let buckets = high_scores
+        .buckets()
+        .cloned()
+        .chain([current.clone()])
+        .collect::<BTreeSet<_>>()
+        .into_iter()
+        .collect::<Vec<_>>();
+    let index = buckets
+        .iter()
+        .position(|bucket| *bucket == current)
+        .unwrap();
+    commands.insert_resource(ShownHighScores { buckets, index });
+
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    width: Val::Percent(100.0),
+                    height: Val::Percent(100.0),
+                    align_items: AlignItems::Center,
+                    justify_content: JustifyContent::Center,
+                    ..default()
+                },
+                ..default()
+            },
+            OnHighScoresMenuScreen,
+        ))
+        .with_children(|parent| {
+            parent
+                .spawn(NodeBundle {
+                    style: Style {
+                        flex_direction: FlexDirection::Column,
+                        align_items: AlignItems::Center,
+                        padding: UiRect::all(Val::Px(10.0)),
+                        ..default()
+                    },
+                    background_color: bevy::color::palettes::css::CRIMSON.into(),
+                    ..default()
+                })
+                .with_children(|parent| {
+                    parent
+                        .spawn(NodeBundle {
+                            style: Style {
+                                align_items: AlignItems::Center,
+                                ..default()
+                            },
+                            ..default()
+                        })
+                        .with_children(|parent| {
+                            for (page, label) in [(-1, "<"), (1, ">")] {
+                                let mut button = parent.spawn((
+                                    ButtonBundle {
+                                        style: button_style.clone(),
+                                        background_color: NORMAL_BUTTON.into(),
+                                        ..default()
+                                    },
+                                    HighScoresPageButton(page),
+                                ));
+                                button.with_children(|parent| {
+                                    parent.spawn(TextBundle::from_section(
+                                        label,
+                                        button_text_style.clone(),
+                                    ));
+                                });
+                                // The table name sits between both arrows
+                                if page < 0 {
+                                    parent.spawn((
+                                        TextBundle::from_section("", button_text_style.clone())
+                                            .with_style(Style {
+                                                width: Val::Px(220.0),
+                                                justify_content: JustifyContent::Center,
+                                                ..default()
+                                            }),
+                                        HighScoresBucketText,
+                                    ));
+                                }
+                            }
+                        });
+
+                    for rank in 0..MAX_HIGH_SCORES {
+                        parent.spawn((
+                            TextBundle::from_section("", row_text_style.clone()).with_style(
+                                Style {
+                                    width: Val::Px(280.0),
+                                    ..default()
+                                },
+                            ),
+                            HighScoreRow(rank),
+                        ));
+                    }
+
+                    parent
+                        .spawn((
+                            ButtonBundle {
+                                style: Style {
+                                    width: Val::Px(200.0),
+                                    ..button_style
+                                },
+                                background_color: NORMAL_BUTTON.into(),
+                                ..default()
+                            },
+                            MenuButtonAction::BackToMainMenu,
+                        ))
+                        .with_children(|parent| {
+                            parent.spawn(TextBundle::from_section("Back", button_text_style));
+                        });
+                });
+        });
+}
+
+fn high_scores_page_button(
+    interaction_query: Query<
+        (&Interaction, &HighScoresPageButton),
+        (Changed<Interaction>, With<Button>),
+    >,
+    mut shown: ResMut<ShownHighScores>,
+) {
+    for (interaction, HighScoresPageButton(page)) in &interaction_query {
+        if *interaction == Interaction::Pressed {
+            let count = shown.buckets.len() as isize;
+            shown.index = (shown.index as isize + page).rem_euclid(count) as usize;
+        }
+    }
+}
+
+fn update_high_scores_table(
+    shown: Res<ShownHighScores>,
+    high_scores: Res<HighScores>,
+    mut bucket_texts: Query<&mut Text, With<HighScoresBucketText>>,
+    mut rows: Query<(&mut Text, &HighScoreRow), Without<HighScoresBucketText>>,
+) {
+    if !shown.is_changed() {
+        return;
+    }
+
+    let bucket = &shown.buckets[shown.index];
+    for mut text in &mut bucket_texts {
+        text.sections[0].value = bucket.clone();
+    }
+
+    let table = high_scores.table(bucket);
+    for (mut text, HighScoreRow(rank)) in &mut rows {
+        text.sections[0].value = match table.get(*rank) {
+            Some(entry) => format!(
+                "{:>2}. {:<3} {:>7}  ({} long)",
+                rank + 1,
+                entry.initials,
+                entry.score,
+                entry.length
+            ),
+            None if *rank == 0 => "No scores yet".to_string(),
+            None => String::new(),
+        };
+    }
+}
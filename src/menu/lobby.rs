@@ -0,0 +1,356 @@
+use bevy::{
+    input::keyboard::{Key, KeyboardInput},
+    prelude::*,
+};
+
+use super::{
+    focus_navigation, move_focus, Focused, MenuButtonAction, MenuState, FOCUS_OUTLINE,
+    NORMAL_BUTTON,
+};
+use crate::{
+    net::{GameSetup, Lobby, DEFAULT_PORT, INPUT_DELAY},
+    sim::level::Level,
+    utils::{despawn_screen, Arena, GameMode, GameState, Seed, SelectedLevel, TEXT_COLOR},
+};
+
+pub(super) fn lobby_menu_plugin(app: &mut App) {
+    app.insert_resource(LobbyAddress(format!("127.0.0.1:{DEFAULT_PORT}")))
+        .add_systems(OnEnter(MenuState::Lobby), lobby_menu_setup)
+        .add_systems(
+            Update,
+            (click_address_field, type_address, lobby_button, poll_lobby)
+                .chain()
+                .after(focus_navigation)
+                .run_if(in_state(MenuState::Lobby)),
+        )
+        .add_systems(
+            OnExit(MenuState::Lobby),
+            (despawn_screen::<OnLobbyMenuScreen>, close_lobby),
+        );
+}
+
+#[derive(Component)]
+struct OnLobbyMenuScreen;
+
+/// Address typed on the lobby screen, `host:port` to join or the port to host on.
+#[derive(Resource)]
+struct LobbyAddress(String);
+
+const MAX_ADDRESS_LENGTH: usize = 40;
+
+// Typing goes to the address while this has the focus
+#[derive(Component)]
+pub(super) struct AddressField;
+
+#[derive(Component)]
+struct AddressText;
+
+#[derive(Component)]
+struct LobbyStatusText;
+
+#[derive(Component, Clone, Copy)]
+enum LobbyButton {
+    Host,
+    Join,
+}
+
+fn lobby_menu_setup(mut commands: Commands, address: Res<LobbyAddress>) {
+    let button_style = Style {
+        width: Val::Px(130.0),
+        height: Val::Px(55.0),
+        margin: UiRect::all(Val::Px(8.0)),
+        justify_content: JustifyContent::Center,
+        align_items: AlignItems::Center,
+        ..default()
+    };
+    let button_text_style = TextStyle {
+        font_size: 32.0,
+        color: TEXT_COLOR,
+        ..default()
+    };
+    let info_text_style = TextStyle {
+        font_size: 22.0,
+        color: TEXT_COLOR,
+        ..default()
+    };
+
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    width: Val::Percent(100.0),
+                    height: Val::Percent(100.0),
+                    align_items: AlignItems::Center,
+                    justify_content: JustifyContent::Center,
+                    ..default()
+                },
+                ..default()
+            },
+            OnLobbyMenuScreen,
+        ))
+        .with_children(|parent| {
+            parent
+                .spawn(NodeBundle {
+                    style: Style {
+                        flex_direction: FlexDirection::Column,
+                        align_items: AlignItems::Center,
+                        padding: UiRect::all(Val::Px(10.0)),
+                        ..default()
+                    },
+                    background_color: bevy::color::palettes::css::CRIMSON.into(),
+                    ..default()
+                })
+                .with_children(|parent| {
+                    parent.spawn(TextBundle::from_section(
+                        "Online game",
+                        button_text_style.clone(),
+                    ));
+                    parent
+                        .spawn((
+                            ButtonBundle {
+                                style: Style {
+                                    width: Val::Px(300.0),
+                                    margin: UiRect::all(Val::Px(10.0)),
+                                    padding: UiRect::all(Val::Px(8.0)),
+                                    ..default()
+                                },
+                                background_color: NORMAL_BUTTON.into(),
+                                ..default()
+                            },
+                            AddressField,
+                            Focused,
+                            Outline::new(Val::Px(3.0), Val::Px(2.0), FOCUS_OUTLINE),
+                        ))
+                        .with_children(|parent| {
+                            parent.spawn((
+                                TextBundle::from_section(
+                                    format!("{}_", address.0),
+                                    info_text_style.clone(),
+                                ),
+                                AddressText,
+                            ));
+                        });
+                    parent.spawn(TextBundle::from_section(
+                        "Hosting listens on the port of the address",
+                        TextStyle {
+                            font_size: 18.0,
+                            ..info_text_style.clone()
+                        },
+                    ));
+
+                    parent
+                        .spawn(NodeBundle {
+                            style: Style {
+                                align_items: AlignItems::Center,
+                                ..default()
+                            },
+                            ..default()
+                        })
+                        .with_children(|parent| {
+                            let spawn_button = |parent: &mut ChildBuilder, action, text| {
+                                parent
+                                    .spawn((
+                                        ButtonBundle {
+                                            style: button_style.clone(),
+                                            background_color: NORMAL_BUTTON.into(),
+                                            ..default()
+                                        },
+                                        action,
+                                    ))
+                                    .with_children(|parent| {
+                                        parent.spawn(TextBundle::from_section(
+                                            text,
+                                            button_text_style.clone(),
+                                        ));
+                                    });
+                            };
+                            spawn_button(parent, LobbyButton::Host, "Host");
+                            spawn_button(parent, LobbyButton::Join, "Join");
+                            parent
+                                .spawn((
+                                    ButtonBundle {
+                                        style: button_style.clone(),
+                                        background_color: NORMAL_BUTTON.into(),
+                                        ..default()
+                                    },
+                                    MenuButtonAction::NewGame,
+                                ))
+                                .with_children(|parent| {
+                                    parent.spawn(TextBundle::from_section(
+                                        "Back",
+                                        button_text_style.clone(),
+                                    ));
+                                });
+                        });
+                    parent.spawn((
+                        TextBundle::from_section("", info_text_style),
+                        LobbyStatusText,
+                    ));
+                });
+        });
+}
+
+// Typing an address uses the same keys as the navigation
+pub(super) fn not_typing_address(field: Query<(), (With<AddressField>, With<Focused>)>) -> bool {
+    field.is_empty()
+}
+
+fn click_address_field(
+    mut commands: Commands,
+    field: Query<
+        (Entity, &Interaction),
+        (Changed<Interaction>, With<AddressField>, Without<Focused>),
+    >,
+    focused: Query<Entity, With<Focused>>,
+) {
+    for (entity, interaction) in &field {
+        if *interaction == Interaction::Pressed {
+            move_focus(&mut commands, focused.get_single().ok(), entity);
+        }
+    }
+}
+
+// Enter, Tab and Escape leave the field for the Host button, from where Back works again
+fn type_address(
+    mut commands: Commands,
+    mut keyboard_events: EventReader<KeyboardInput>,
+    mut address: ResMut<LobbyAddress>,
+    field: Query<(Entity, Ref<Focused>), With<AddressField>>,
+    buttons: Query<(Entity, &LobbyButton)>,
+    mut texts: Query<&mut Text, With<AddressText>>,
+) {
+    // The key that moved the focus here was meant for the navigation
+    let Some((field, _)) = field
+        .get_single()
+        .ok()
+        .filter(|(_, focus)| !focus.is_added())
+    else {
+        keyboard_events.clear();
+        return;
+    };
+
+    for event in keyboard_events.read() {
+        if !event.state.is_pressed() {
+            continue;
+        }
+
+        match &event.logical_key {
+            Key::Character(characters) => {
+                for c in characters
+                    .chars()
+                    .filter(|c| c.is_ascii_alphanumeric() || ".:-[]".contains(*c))
+                {
+                    if address.0.len() < MAX_ADDRESS_LENGTH {
+                        address.0.push(c);
+                    }
+                }
+            }
+            Key::Backspace => {
+                address.0.pop();
+            }
+            Key::Enter | Key::Tab | Key::Escape => {
+                if let Some((host, _)) = buttons
+                    .iter()
+                    .find(|(_, button)| matches!(button, LobbyButton::Host))
+                {
+                    move_focus(&mut commands, Some(field), host);
+                }
+                break;
+            }
+            _ => {}
+        }
+    }
+
+    if address.is_changed() {
+        for mut text in &mut texts {
+            text.sections[0].value = format!("{}_", address.0);
+        }
+    }
+}
+
+// The host picks the game: the mode and level of the new game screen
+fn lobby_button(
+    mut commands: Commands,
+    interaction_query: Query<(&Interaction, &LobbyButton), (Changed<Interaction>, With<Button>)>,
+    address: Res<LobbyAddress>,
+    seed: Res<Seed>,
+    mode: Res<GameMode>,
+    arena: Res<Arena>,
+    selected_level: Res<SelectedLevel>,
+    mut texts: Query<&mut Text, With<LobbyStatusText>>,
+) {
+    for (interaction, button) in &interaction_query {
+        if *interaction != Interaction::Pressed {
+            continue;
+        }
+
+        let lobby = match button {
+            LobbyButton::Host => {
+                let port = address
+                    .0
+                    .rsplit_once(':')
+                    .and_then(|(_, port)| port.parse().ok())
+                    .unwrap_or(DEFAULT_PORT);
+                let level = match &selected_level.0 {
+                    Some(name) => Level::load_named(name),
+                    None => Ok(Level::open(*arena)),
+                };
+                level.and_then(|level| {
+                    Lobby::host(
+                        port,
+                        GameSetup {
+                            seed: seed.0.unwrap_or_else(rand::random),
+                            mode: *mode,
+                            level,
+                            input_delay: INPUT_DELAY,
+                        },
+                    )
+                })
+            }
+            LobbyButton::Join => Lobby::join(&address.0),
+        };
+        let status = match lobby {
+            Ok(lobby) => {
+                let status = lobby.describe();
+                commands.insert_resource(lobby);
+                status
+            }
+            Err(err) => format!("Error: {err}"),
+        };
+        for mut text in &mut texts {
+            text.sections[0].value = status.clone();
+        }
+    }
+}
+
+fn poll_lobby(
+    mut commands: Commands,
+    lobby: Option<ResMut<Lobby>>,
+    mut game_state: ResMut<NextState<GameState>>,
+    mut menu_state: ResMut<NextState<MenuState>>,
+    mut texts: Query<&mut Text, With<LobbyStatusText>>,
+) {
+    let Some(mut lobby) = lobby else {
+        return;
+    };
+
+    match lobby.poll() {
+        Ok(Some(session)) => {
+            commands.remove_resource::<Lobby>();
+            commands.insert_resource(session);
+            game_state.set(GameState::Game);
+            menu_state.set(MenuState::Disabled);
+        }
+        Ok(None) => {}
+        Err(err) => {
+            commands.remove_resource::<Lobby>();
+            for mut text in &mut texts {
+                text.sections[0].value = format!("Error: {err}");
+            }
+        }
+    }
+}
+
+fn close_lobby(mut commands: Commands) {
+    commands.remove_resource::<Lobby>();
+}
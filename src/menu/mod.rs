@@ -0,0 +1,1532 @@
+mod campaign;
+mod high_scores;
+mod lobby;
+mod replays;
+
+use bevy::{app::AppExit, prelude::*};
+
+use self::{
+    campaign::campaign_menu_plugin,
+    high_scores::high_scores_menu_plugin,
+    lobby::{lobby_menu_plugin, not_typing_address},
+    replays::replays_menu_plugin,
+};
+use crate::{
+    campaign::CampaignRun,
+    controls::{Action, ActionInput, BindingSlot, Controls, KEY_SLOTS},
+    net::NetSession,
+    settings::{apply_theme, save_settings},
+    sim::level::{list_levels, Level},
+    utils::{
+        despawn_screen, Arena, Direction, GameMode, GameState, MusicVolume, Players, SelectedLevel,
+        SfxVolume, Theme, Volume, TEXT_COLOR,
+    },
+};
+
+#[derive(Component)]
+struct OnMainMenuScreen;
+#[derive(Component)]
+struct OnNewGameMenuScreen;
+#[derive(Component)]
+struct OnSettingsMenuScreen;
+#[derive(Component)]
+struct OnSoundSettingsMenuScreen;
+#[derive(Component)]
+struct OnArenaSettingsMenuScreen;
+#[derive(Component)]
+struct OnControlsSettingsMenuScreen;
+
+#[derive(Clone, Copy, Default, Eq, PartialEq, Debug, Hash, States)]
+enum MenuState {
+    Main,
+    NewGame,
+    Campaign,
+    Replays,
+    HighScores,
+    Settings,
+    SettingsSound,
+    SettingsArena,
+    SettingsControls,
+    Lobby,
+    #[default]
+    Disabled,
+}
+
+pub fn menu_plugin(app: &mut App) {
+    app.init_state::<MenuState>()
+        .add_systems(OnEnter(GameState::Menu), menu_setup)
+        .add_systems(OnEnter(MenuState::Main), main_menu_setup)
+        .add_systems(OnExit(MenuState::Main), despawn_screen::<OnMainMenuScreen>)
+        .add_systems(OnEnter(MenuState::NewGame), new_game_menu_setup)
+        .add_systems(
+            Update,
+            (
+                setting_button::<GameMode>,
+                setting_button::<Players>,
+                update_mode_description,
+                level_button,
+                update_level_text,
+            )
+                .chain()
+                .run_if(in_state(MenuState::NewGame)),
+        )
+        .add_systems(
+            OnExit(MenuState::NewGame),
+            (despawn_screen::<OnNewGameMenuScreen>, save_settings),
+        )
+        .add_plugins((
+            campaign_menu_plugin,
+            lobby_menu_plugin,
+            replays_menu_plugin,
+            high_scores_menu_plugin,
+        ))
+        .add_systems(OnEnter(MenuState::Settings), settings_menu_setup)
+        .add_systems(
+            Update,
+            (setting_button::<Theme>, apply_theme)
+                .chain()
+                .run_if(in_state(MenuState::Settings)),
+        )
+        .add_systems(
+            OnExit(MenuState::Settings),
+            (despawn_screen::<OnSettingsMenuScreen>, save_settings),
+        )
+        .add_systems(OnEnter(MenuState::SettingsSound), settings_sound_menu_setup)
+        .add_systems(
+            Update,
+            (
+                setting_button::<Volume>,
+                setting_button::<SfxVolume>,
+                setting_button::<MusicVolume>,
+            )
+                .run_if(in_state(MenuState::SettingsSound)),
+        )
+        .add_systems(
+            OnExit(MenuState::SettingsSound),
+            (despawn_screen::<OnSoundSettingsMenuScreen>, save_settings),
+        )
+        .add_systems(OnEnter(MenuState::SettingsArena), settings_arena_menu_setup)
+        .add_systems(
+            Update,
+            (
+                setting_button::<Arena>,
+                arena_size_button,
+                update_arena_size_text,
+            )
+                .run_if(in_state(MenuState::SettingsArena)),
+        )
+        .add_systems(
+            OnExit(MenuState::SettingsArena),
+            (despawn_screen::<OnArenaSettingsMenuScreen>, save_settings),
+        )
+        .add_systems(
+            OnEnter(MenuState::SettingsControls),
+            settings_controls_menu_setup,
+        )
+        .add_systems(
+            Update,
+            (
+                release_focus_press,
+                (menu_back, focus_navigation).run_if(not_typing_address),
+            )
+                .chain()
+                .run_if(in_state(GameState::Menu).and_then(not_rebinding))
+                .before(binding_button),
+        )
+        .add_systems(
+            Update,
+            (binding_button, capture_binding, update_binding_texts)
+                .chain()
+                .run_if(in_state(MenuState::SettingsControls)),
+        )
+        .add_systems(
+            OnExit(MenuState::SettingsControls),
+            (
+                despawn_screen::<OnControlsSettingsMenuScreen>,
+                stop_rebinding,
+                save_settings,
+            ),
+        )
+        .add_systems(
+            Update,
+            (menu_action, button_system).run_if(in_state(GameState::Menu)),
+        );
+}
+
+pub(crate) const NORMAL_BUTTON: Color = Color::srgb(0.15, 0.15, 0.15);
+const HOVERED_BUTTON: Color = Color::srgb(0.25, 0.25, 0.25);
+const HOVERED_PRESSED_BUTTON: Color = Color::srgb(0.25, 0.65, 0.25);
+const PRESSED_BUTTON: Color = Color::srgb(0.35, 0.75, 0.35);
+
+const FOCUS_OUTLINE: Color = Color::srgb(1.0, 0.84, 0.0);
+
+#[derive(Component)]
+pub(crate) struct SelectedOption;
+
+/// Button keyboard and gamepad input goes to, outlined so it shows over the selected colors.
+#[derive(Component)]
+pub(crate) struct Focused;
+
+// Pressed with Confirm, released on the next frame like a mouse click
+#[derive(Component)]
+pub(crate) struct FocusPress;
+
+// All actions that can be triggered from a button click
+#[derive(Component)]
+enum MenuButtonAction {
+    NewGame,
+    Play,
+    EditLevel,
+    Campaign,
+    Replays,
+    HighScores,
+    Settings,
+    SettingsSound,
+    SettingsArena,
+    SettingsControls,
+    ResetControls,
+    BackToMainMenu,
+    BackToSettings,
+    Quit,
+}
+
+// Grows or shrinks the custom arena by this many cells
+#[derive(Component)]
+enum ArenaSizeButton {
+    Width(i32),
+    Height(i32),
+}
+
+#[derive(Component)]
+struct ArenaSizeText;
+
+#[derive(Component)]
+struct ModeDescriptionText;
+
+// Moves this many levels forward or back in the list, the open arena coming first
+#[derive(Component)]
+struct LevelButton(isize);
+
+#[derive(Component)]
+struct LevelText;
+
+// Waits for the next key or gamepad button to bind it in this slot
+#[derive(Component, Clone, Copy, PartialEq, Eq)]
+struct BindingButton {
+    action: Action,
+    slot: BindingSlot,
+}
+
+#[derive(Component)]
+struct BindingText(BindingButton);
+
+// The binding slot picked in the controls menu, waiting for an input
+#[derive(Resource, Default)]
+struct Rebinding(Option<BindingButton>);
+
+// This system handles changing all buttons color based on mouse interaction
+pub(crate) fn button_system(
+    mut interaction_query: Query<
+        (&Interaction, &mut BackgroundColor, Option<&SelectedOption>),
+        (Changed<Interaction>, With<Button>),
+    >,
+) {
+    for (interaction, mut color, selected) in &mut interaction_query {
+        *color = match (*interaction, selected) {
+            (Interaction::Pressed, _) | (Interaction::None, Some(_)) => PRESSED_BUTTON.into(),
+            (Interaction::Hovered, Some(_)) => HOVERED_PRESSED_BUTTON.into(),
+            (Interaction::Hovered, None) => HOVERED_BUTTON.into(),
+            (Interaction::None, None) => NORMAL_BUTTON.into(),
+        }
+    }
+}
+
+// Focus and Confirm stand in for the mouse, so every button works without one
+pub(crate) fn focus_navigation(
+    mut commands: Commands,
+    actions: Res<ActionInput>,
+    keys: Res<ButtonInput<KeyCode>>,
+    mut buttons: Query<(Entity, &GlobalTransform, &ViewVisibility, &mut Interaction), With<Button>>,
+    focused: Query<Entity, With<Focused>>,
+) {
+    let focused = focused.get_single().ok();
+    if actions.just_pressed(Action::Confirm) {
+        if let Some((entity, _, _, mut interaction)) =
+            focused.and_then(|entity| buttons.get_mut(entity).ok())
+        {
+            *interaction = Interaction::Pressed;
+            commands.entity(entity).insert(FocusPress);
+            return;
+        }
+    }
+
+    let shift = keys.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight]);
+    let direction = actions.iter_just_pressed().find_map(Action::direction);
+    let tab = keys.just_pressed(KeyCode::Tab);
+    if !tab && direction.is_none() && !actions.just_pressed(Action::Confirm) {
+        return;
+    }
+
+    // Reading order, UI coordinates grow downwards
+    let mut positions = buttons
+        .iter()
+        .filter(|(_, _, visibility, _)| visibility.get())
+        .map(|(entity, transform, _, _)| (entity, transform.translation().truncate()))
+        .collect::<Vec<_>>();
+    if positions.is_empty() {
+        return;
+    }
+    positions.sort_by(|(_, a), (_, b)| a.y.total_cmp(&b.y).then(a.x.total_cmp(&b.x)));
+
+    let current =
+        focused.and_then(|focused| positions.iter().position(|(entity, _)| *entity == focused));
+    let next = match current {
+        // The first press only shows where the focus is
+        None => 0,
+        Some(index) if tab && shift => (index + positions.len() - 1) % positions.len(),
+        Some(index) if tab => (index + 1) % positions.len(),
+        Some(index) => direction
+            .and_then(|direction| nearest_towards(&positions, index, direction))
+            .unwrap_or(index),
+    };
+
+    move_focus(&mut commands, focused, positions[next].0);
+}
+
+pub(crate) fn move_focus(commands: &mut Commands, from: Option<Entity>, to: Entity) {
+    if let Some(from) = from {
+        commands.entity(from).remove::<(Focused, Outline)>();
+    }
+    commands.entity(to).insert((
+        Focused,
+        Outline::new(Val::Px(3.0), Val::Px(2.0), FOCUS_OUTLINE),
+    ));
+}
+
+// Closest button in that direction, favoring the ones in line with the current one
+fn nearest_towards(
+    positions: &[(Entity, Vec2)],
+    from: usize,
+    direction: Direction,
+) -> Option<usize> {
+    let axis = match direction {
+        Direction::Up => Vec2::NEG_Y,
+        Direction::Down => Vec2::Y,
+        Direction::Left => Vec2::NEG_X,
+        Direction::Right => Vec2::X,
+    };
+    let origin = positions[from].1;
+    positions
+        .iter()
+        .enumerate()
+        .filter_map(|(index, (_, position))| {
+            let offset = *position - origin;
+            let along = offset.dot(axis);
+            (along > 1.).then_some((index, along + offset.perp_dot(axis).abs() * 2.))
+        })
+        .min_by(|(_, a), (_, b)| a.total_cmp(b))
+        .map(|(index, _)| index)
+}
+
+pub(crate) fn release_focus_press(
+    mut commands: Commands,
+    mut pressed: Query<(Entity, &mut Interaction), With<FocusPress>>,
+) {
+    for (entity, mut interaction) in &mut pressed {
+        *interaction = Interaction::None;
+        commands.entity(entity).remove::<FocusPress>();
+    }
+}
+
+// Back goes up one screen, like the Back buttons
+fn menu_back(
+    actions: Res<ActionInput>,
+    menu_state: Res<State<MenuState>>,
+    mut next_menu_state: ResMut<NextState<MenuState>>,
+) {
+    if !actions.just_pressed(Action::Back) {
+        return;
+    }
+
+    match menu_state.get() {
+        MenuState::NewGame
+        | MenuState::Campaign
+        | MenuState::Replays
+        | MenuState::HighScores
+        | MenuState::Settings => next_menu_state.set(MenuState::Main),
+        MenuState::SettingsSound | MenuState::SettingsArena | MenuState::SettingsControls => {
+            next_menu_state.set(MenuState::Settings)
+        }
+        MenuState::Lobby => next_menu_state.set(MenuState::NewGame),
+        MenuState::Main | MenuState::Disabled => {}
+    }
+}
+
+pub(crate) fn setting_button<T: Resource + Component + PartialEq + Copy>(
+    interaction_query: Query<(&Interaction, &T, Entity), (Changed<Interaction>, With<Button>)>,
+    mut selected_query: Query<(Entity, &mut BackgroundColor), (With<SelectedOption>, With<T>)>,
+    mut commands: Commands,
+    mut setting: ResMut<T>,
+) {
+    for (interaction, button_setting, entity) in &interaction_query {
+        if *interaction == Interaction::Pressed && *setting != *button_setting {
+            // A custom value may not match any of the buttons
+            if let Ok((previous_button, mut previous_color)) = selected_query.get_single_mut() {
+                *previous_color = NORMAL_BUTTON.into();
+                commands.entity(previous_button).remove::<SelectedOption>();
+            }
+            commands.entity(entity).insert(SelectedOption);
+            *setting = *button_setting;
+        }
+    }
+}
+
+// Coming back to the menu also leaves the campaign and the network game
+fn menu_setup(mut commands: Commands, mut menu_state: ResMut<NextState<MenuState>>) {
+    commands.remove_resource::<CampaignRun>();
+    commands.remove_resource::<NetSession>();
+    menu_state.set(MenuState::Main);
+}
+
+fn main_menu_setup(mut commands: Commands, asset_server: Res<AssetServer>) {
+    // Common style for all buttons on the screen
+    let button_style = Style {
+        width: Val::Px(250.0),
+        height: Val::Px(48.0),
+        margin: UiRect::all(Val::Px(6.0)),
+        justify_content: JustifyContent::Center,
+        align_items: AlignItems::Center,
+        ..default()
+    };
+    let button_icon_style = Style {
+        width: Val::Px(30.0),
+        // This takes the icons out of the flexbox flow, to be positioned exactly
+        position_type: PositionType::Absolute,
+        // The icon will be close to the left border of the button
+        left: Val::Px(10.0),
+        ..default()
+    };
+    let button_text_style = TextStyle {
+        font_size: 40.0,
+        color: TEXT_COLOR,
+        ..default()
+    };
+
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    width: Val::Percent(100.0),
+                    height: Val::Percent(100.0),
+                    align_items: AlignItems::Center,
+                    justify_content: JustifyContent::Center,
+                    ..default()
+                },
+                ..default()
+            },
+            OnMainMenuScreen,
+        ))
+        .with_children(|parent| {
+            parent
+                .spawn(NodeBundle {
+                    style: Style {
+                        flex_direction: FlexDirection::Column,
+                        align_items: AlignItems::Center,
+                        ..default()
+                    },
+                    background_color: bevy::color::palettes::css::CRIMSON.into(),
+                    ..default()
+                })
+                .with_children(|parent| {
+                    // Display the game name
+                    parent.spawn(
+                        TextBundle::from_section(
+                            "Snake game",
+                            TextStyle {
+                                font_size: 80.0,
+                                color: TEXT_COLOR,
+                                ..default()
+                            },
+                        )
+                        .with_style(Style {
+                            margin: UiRect::all(Val::Px(10.0)),
+                            ..default()
+                        }),
+                    );
+
+                    // Display a button for each action available from the main menu:
+                    // - new game
+                    // - campaign
+                    // - replays
+                    // - high scores
+                    // - settings
+                    // - quit
+                    parent
+                        .spawn((
+                            ButtonBundle {
+                                style: button_style.clone(),
+                                background_color: NORMAL_BUTTON.into(),
+                                ..default()
+                            },
+                            MenuButtonAction::NewGame,
+                        ))
+                        .with_children(|parent| {
+                            let icon = asset_server.load("textures/settings_icon.png");
+                            parent.spawn(ImageBundle {
+                                style: button_icon_style.clone(),
+                                image: UiImage::new(icon),
+                                ..default()
+                            });
+                            parent.spawn(TextBundle::from_section(
+                                "New Game",
+                                button_text_style.clone(),
+                            ));
+                        });
+                    parent
+                        .spawn((
+                            ButtonBundle {
+                                style: button_style.clone(),
+                                background_color: NORMAL_BUTTON.into(),
+                                ..default()
+                            },
+                            MenuButtonAction::Campaign,
+                        ))
+                        .with_children(|parent| {
+                            let icon = asset_server.load("textures/settings_icon.png");
+                            parent.spawn(ImageBundle {
+                                style: button_icon_style.clone(),
+                                image: UiImage::new(icon),
+                                ..default()
+                            });
+                            parent.spawn(TextBundle::from_section(
+                                "Campaign",
+                                button_text_style.clone(),
+                            ));
+                        });
+                    parent
+                        .spawn((
+                            ButtonBundle {
+                                style: button_style.clone(),
+                                background_color: NORMAL_BUTTON.into(),
+                                ..default()
+                            },
+                            MenuButtonAction::Replays,
+                        ))
+                        .with_children(|parent| {
+                            let icon = asset_server.load("textures/settings_icon.png");
+                            parent.spawn(ImageBundle {
+                                style: button_icon_style.clone(),
+                                image: UiImage::new(icon),
+                                ..default()
+                            });
+                            parent.spawn(TextBundle::from_section(
+                                "Replays",
+                                button_text_style.clone(),
+                            ));
+                        });
+                    parent
+                        .spawn((
+                            ButtonBundle {
+                                style: button_style.clone(),
+                                background_color: NORMAL_BUTTON.into(),
+                                ..default()
+                            },
+                            MenuButtonAction::HighScores,
+                        ))
+                        .with_children(|parent| {
+                            let icon = asset_server.load("textures/settings_icon.png");
+                            parent.spawn(ImageBundle {
+                                style: button_icon_style.clone(),
+                                image: UiImage::new(icon),
+                                ..default()
+                            });
+                            parent.spawn(TextBundle::from_section(
+                                "High Scores",
+                                button_text_style.clone(),
+                            ));
+                        });
+                    parent
+                        .spawn((
+                            ButtonBundle {
+                                style: button_style.clone(),
+                                background_color: NORMAL_BUTTON.into(),
+                                ..default()
+                            },
+                            MenuButtonAction::Settings,
+                        ))
+                        .with_children(|parent| {
+                            let icon = asset_server.load("textures/settings_icon.png");
+                            parent.spawn(ImageBundle {
+                                style: button_icon_style.clone(),
+                                image: UiImage::new(icon),
+                                ..default()
+                            });
+                            parent.spawn(TextBundle::from_section(
+                                "Settings",
+                                button_text_style.clone(),
+                            ));
+                        });
+                    parent
+                        .spawn((
+                            ButtonBundle {
+                                style: button_style,
+                                background_color: NORMAL_BUTTON.into(),
+                                ..default()
+                            },
+                            MenuButtonAction::Quit,
+                        ))
+                        .with_children(|parent| {
+                            let icon = asset_server.load("textures/settings_icon.png");
+                            parent.spawn(ImageBundle {
+                                style: button_icon_style,
+                                image: UiImage::new(icon),
+                                ..default()
+                            });
+                            parent.spawn(TextBundle::from_section("Quit", button_text_style));
+                        });
+                });
+        });
+}
+fn mode_description(mode: GameMode) -> &'static str {
+    match mode {
+        GameMode::Classic => "Hitting a wall ends the run",
+        GameMode::Wrap => "No walls, edges lead to the other side",
+    }
+}
+
+// Name and size of the board the next game is played on
+fn level_label(level: &SelectedLevel, arena: Arena) -> String {
+    let Some(name) = &level.0 else {
+        return format!("Open arena {} x {}", arena.width, arena.height);
+    };
+    match Level::load_named(name) {
+        Ok(level) => format!("{name} {} x {}", level.arena.width, level.arena.height),
+        Err(_) => format!("{name} (missing)"),
+    }
+}
+
+fn new_game_menu_setup(
+    mut commands: Commands,
+    mode: Res<GameMode>,
+    players: Res<Players>,
+    arena: Res<Arena>,
+    level: Res<SelectedLevel>,
+) {
+    let button_style = Style {
+        width: Val::Px(130.0),
+        height: Val::Px(55.0),
+        margin: UiRect::all(Val::Px(8.0)),
+        justify_content: JustifyContent::Center,
+        align_items: AlignItems::Center,
+        ..default()
+    };
+    let button_text_style = TextStyle {
+        font_size: 32.0,
+        color: TEXT_COLOR,
+        ..default()
+    };
+    let info_text_style = TextStyle {
+        font_size: 22.0,
+        color: TEXT_COLOR,
+        ..default()
+    };
+    // Four choices of players fit on a row with narrower buttons
+    let players_button_style = Style {
+        width: Val::Px(105.0),
+        margin: UiRect::all(Val::Px(4.0)),
+        ..button_style.clone()
+    };
+    let players_text_style = TextStyle {
+        font_size: 26.0,
+        ..button_text_style.clone()
+    };
+    let small_button_style = Style {
+        width: Val::Px(40.0),
+        height: Val::Px(40.0),
+        margin: UiRect::all(Val::Px(8.0)),
+        justify_content: JustifyContent::Center,
+        align_items: AlignItems::Center,
+        ..default()
+    };
+    let row_style = Style {
+        align_items: AlignItems::Center,
+        ..default()
+    };
+
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    width: Val::Percent(100.0),
+                    height: Val::Percent(100.0),
+                    align_items: AlignItems::Center,
+                    justify_content: JustifyContent::Center,
+                    ..default()
+                },
+                ..default()
+            },
+            OnNewGameMenuScreen,
+        ))
+        .with_children(|parent| {
+            parent
+                .spawn(NodeBundle {
+                    style: Style {
+                        flex_direction: FlexDirection::Column,
+                        align_items: AlignItems::Center,
+                        padding: UiRect::all(Val::Px(10.0)),
+                        ..default()
+                    },
+                    background_color: bevy::color::palettes::css::CRIMSON.into(),
+                    ..default()
+                })
+                .with_children(|parent| {
+                    parent.spawn(TextBundle::from_section("Mode", button_text_style.clone()));
+                    parent
+                        .spawn(NodeBundle {
+                            style: row_style.clone(),
+                            ..default()
+                        })
+                        .with_children(|parent| {
+                            for game_mode in GameMode::ALL {
+                                let mut entity = parent.spawn((
+                                    ButtonBundle {
+                                        style: button_style.clone(),
+                                        background_color: NORMAL_BUTTON.into(),
+                                        ..default()
+                                    },
+                                    game_mode,
+                                ));
+                                entity.with_children(|parent| {
+                                    parent.spawn(TextBundle::from_section(
+                                        game_mode.name(),
+                                        button_text_style.clone(),
+                                    ));
+                                });
+                                if *mode == game_mode {
+                                    entity.insert(SelectedOption);
+                                }
+                            }
+                        });
+                    parent.spawn((
+                        TextBundle::from_section(mode_description(*mode), info_text_style.clone()),
+                        ModeDescriptionText,
+                    ));
+                    parent
+                        .spawn(NodeBundle {
+                            style: row_style.clone(),
+                            ..default()
+                        })
+                        .with_children(|parent| {
+                            for option in Players::ALL {
+                                let mut entity = parent.spawn((
+                                    ButtonBundle {
+                                        style: players_button_style.clone(),
+                                        background_color: NORMAL_BUTTON.into(),
+                                        ..default()
+                                    },
+                                    option,
+                                ));
+                                entity.with_children(|parent| {
+                                    parent.spawn(TextBundle::from_section(
+                                        option.name(),
+                                        players_text_style.clone(),
+                                    ));
+                                });
+                                if *players == option {
+                                    entity.insert(SelectedOption);
+                                }
+                            }
+                        });
+                    // The open arena size is picked in the settings
+                    parent
+                        .spawn(NodeBundle {
+                            style: row_style.clone(),
+                            ..default()
+                        })
+                        .with_children(|parent| {
+                            let spawn_button = |parent: &mut ChildBuilder, step, label| {
+                                parent
+                                    .spawn((
+                                        ButtonBundle {
+                                            style: small_button_style.clone(),
+                                            background_color: NORMAL_BUTTON.into(),
+                                            ..default()
+                                        },
+                                        LevelButton(step),
+                                    ))
+                                    .with_children(|parent| {
+                                        parent.spawn(TextBundle::from_section(
+                                            label,
+                                            button_text_style.clone(),
+                                        ));
+                                    });
+                            };
+                            spawn_button(parent, -1, "<");
+                            parent.spawn((
+                                TextBundle::from_section(
+                                    level_label(&level, *arena),
+                                    info_text_style.clone(),
+                                )
+                                .with_text_justify(JustifyText::Center)
+                                .with_style(Style {
+                                    width: Val::Px(220.0),
+                                    ..default()
+                                }),
+                                LevelText,
+                            ));
+                            spawn_button(parent, 1, ">");
+                        });
+
+                    parent
+                        .spawn(NodeBundle {
+                            style: row_style,
+                            ..default()
+                        })
+                        .with_children(|parent| {
+                            for (action, text) in [
+                                (MenuButtonAction::Play, "Start"),
+                                (MenuButtonAction::EditLevel, "Edit"),
+                                (MenuButtonAction::BackToMainMenu, "Back"),
+                            ] {
+                                parent
+                                    .spawn((
+                                        ButtonBundle {
+                                            style: button_style.clone(),
+                                            background_color: NORMAL_BUTTON.into(),
+                                            ..default()
+                                        },
+                                        action,
+                                    ))
+                                    .with_children(|parent| {
+                                        parent.spawn(TextBundle::from_section(
+                                            text,
+                                            button_text_style.clone(),
+                                        ));
+                                    });
+                            }
+                        });
+                });
+        });
+}
+
+fn update_mode_description(
+    mode: Res<GameMode>,
+    mut texts: Query<&mut Text, With<ModeDescriptionText>>,
+) {
+    if !mode.is_changed() {
+        return;
+    }
+
+    for mut text in &mut texts {
+        text.sections[0].value = mode_description(*mode).to_string();
+    }
+}
+
+fn level_button(
+    interaction_query: Query<(&Interaction, &LevelButton), (Changed<Interaction>, With<Button>)>,
+    mut level: ResMut<SelectedLevel>,
+) {
+    for (interaction, level_button) in &interaction_query {
+        if *interaction != Interaction::Pressed {
+            continue;
+        }
+
+        let choices = [None]
+            .into_iter()
+            .chain(list_levels().into_iter().map(Some))
+            .collect::<Vec<_>>();
+        let current = choices
+            .iter()
+            .position(|choice| *choice == level.0)
+            .unwrap_or(0);
+        let next = (current as isize + level_button.0).rem_euclid(choices.len() as isize);
+        level.0 = choices[next as usize].clone();
+    }
+}
+
+fn update_level_text(
+    level: Res<SelectedLevel>,
+    arena: Res<Arena>,
+    mut texts: Query<&mut Text, With<LevelText>>,
+) {
+    if !level.is_changed() {
+        return;
+    }
+
+    for mut text in &mut texts {
+        text.sections[0].value = level_label(&level, *arena);
+    }
+}
+
+fn settings_menu_setup(mut commands: Commands, theme: Res<Theme>) {
+    let button_style = Style {
+        width: Val::Px(200.0),
+        height: Val::Px(65.0),
+        margin: UiRect::all(Val::Px(8.0)),
+        justify_content: JustifyContent::Center,
+        align_items: AlignItems::Center,
+        ..default()
+    };
+    let theme_button_style = Style {
+        width: Val::Px(120.0),
+        height: Val::Px(50.0),
+        ..button_style.clone()
+    };
+
+    let button_text_style = TextStyle {
+        font_size: 40.0,
+        color: TEXT_COLOR,
+        ..default()
+    };
+    let theme_text_style = TextStyle {
+        font_size: 30.0,
+        ..button_text_style.clone()
+    };
+
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    width: Val::Percent(100.0),
+                    height: Val::Percent(100.0),
+                    align_items: AlignItems::Center,
+                    justify_content: JustifyContent::Center,
+                    ..default()
+                },
+                ..default()
+            },
+            OnSettingsMenuScreen,
+        ))
+        .with_children(|parent| {
+            parent
+                .spawn(NodeBundle {
+                    style: Style {
+                        flex_direction: FlexDirection::Column,
+                        align_items: AlignItems::Center,
+                        ..default()
+                    },
+                    background_color: bevy::color::palettes::css::CRIMSON.into(),
+                    ..default()
+                })
+                .with_children(|parent| {
+                    parent.spawn(TextBundle::from_section("Theme", theme_text_style.clone()));
+                    parent.spawn(NodeBundle::default()).with_children(|parent| {
+                        for option in Theme::ALL {
+                            let mut entity = parent.spawn((
+                                ButtonBundle {
+                                    style: theme_button_style.clone(),
+                                    background_color: NORMAL_BUTTON.into(),
+                                    ..default()
+                                },
+                                option,
+                            ));
+                            entity.with_children(|parent| {
+                                parent.spawn(TextBundle::from_section(
+                                    option.name(),
+                                    theme_text_style.clone(),
+                                ));
+                            });
+                            if *theme == option {
+                                entity.insert(SelectedOption);
+                            }
+                        }
+                    });
+
+                    for (action, text) in [
+                        (MenuButtonAction::SettingsSound, "Sound"),
+                        (MenuButtonAction::SettingsArena, "Arena"),
+                        (MenuButtonAction::SettingsControls, "Controls"),
+                        (MenuButtonAction::BackToMainMenu, "Back"),
+                    ] {
+                        parent
+                            .spawn((
+                                ButtonBundle {
+                                    style: button_style.clone(),
+                                    background_color: NORMAL_BUTTON.into(),
+                                    ..default()
+                                },
+                                action,
+                            ))
+                            .with_children(|parent| {
+                                parent.spawn(TextBundle::from_section(
+                                    text,
+                                    button_text_style.clone(),
+                                ));
+                            });
+                    }
+                });
+        });
+}
+fn settings_sound_menu_setup(
+    mut commands: Commands,
+    volume: Res<Volume>,
+    sfx_volume: Res<SfxVolume>,
+    music_volume: Res<MusicVolume>,
+) {
+    let button_style = Style {
+        width: Val::Px(200.0),
+        height: Val::Px(65.0),
+        margin: UiRect::all(Val::Px(20.0)),
+        justify_content: JustifyContent::Center,
+        align_items: AlignItems::Center,
+        ..default()
+    };
+    let button_text_style = TextStyle {
+        font_size: 40.0,
+        color: TEXT_COLOR,
+        ..default()
+    };
+
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    width: Val::Percent(100.0),
+                    height: Val::Percent(100.0),
+                    align_items: AlignItems::Center,
+                    justify_content: JustifyContent::Center,
+                    ..default()
+                },
+                ..default()
+            },
+            OnSoundSettingsMenuScreen,
+        ))
+        .with_children(|parent| {
+            parent
+                .spawn(NodeBundle {
+                    style: Style {
+                        flex_direction: FlexDirection::Column,
+                        align_items: AlignItems::Center,
+                        padding: UiRect::all(Val::Px(10.0)),
+                        ..default()
+                    },
+                    background_color: bevy::color::palettes::css::CRIMSON.into(),
+                    ..default()
+                })
+                .with_children(|parent| {
+                    // One slider per mixer channel
+                    volume_slider(parent, "Master", *volume, Volume);
+                    volume_slider(parent, "Effects", *sfx_volume, SfxVolume);
+                    volume_slider(parent, "Music", *music_volume, MusicVolume);
+
+                    parent
+                        .spawn((
+                            ButtonBundle {
+                                style: button_style,
+                                background_color: NORMAL_BUTTON.into(),
+                                ..default()
+                            },
+                            MenuButtonAction::BackToSettings,
+                        ))
+                        .with_children(|parent| {
+                            parent.spawn(TextBundle::from_section("Back", button_text_style));
+                        });
+                });
+        });
+}
+
+// A row of buttons picking a volume from 0 to 9
+fn volume_slider<T: Component + PartialEq>(
+    parent: &mut ChildBuilder,
+    label: &str,
+    current: T,
+    setting: fn(u32) -> T,
+) {
+    parent
+        .spawn(NodeBundle {
+            style: Style {
+                align_items: AlignItems::Center,
+                margin: UiRect::vertical(Val::Px(8.0)),
+                ..default()
+            },
+            ..default()
+        })
+        .with_children(|parent| {
+            parent.spawn(
+                TextBundle::from_section(
+                    label,
+                    TextStyle {
+                        font_size: 30.0,
+                        color: TEXT_COLOR,
+                        ..default()
+                    },
+                )
+                .with_style(Style {
+                    width: Val::Px(110.0),
+                    ..default()
+                }),
+            );
+            for volume_setting in [0, 1, 2, 3, 4, 5, 6, 7, 8, 9] {
+                let mut entity = parent.spawn((
+                    ButtonBundle {
+                        style: Style {
+                            width: Val::Px(26.0),
+                            height: Val::Px(45.0),
+                            margin: UiRect::all(Val::Px(4.0)),
+                            ..default()
+                        },
+                        background_color: NORMAL_BUTTON.into(),
+                        ..default()
+                    },
+                    setting(volume_setting),
+                ));
+                if current == setting(volume_setting) {
+                    entity.insert(SelectedOption);
+                }
+            }
+        });
+}
+
+fn settings_arena_menu_setup(mut commands: Commands, arena: Res<Arena>) {
+    let button_style = Style {
+        width: Val::Px(130.0),
+        height: Val::Px(50.0),
+        margin: UiRect::all(Val::Px(8.0)),
+        justify_content: JustifyContent::Center,
+        align_items: AlignItems::Center,
+        ..default()
+    };
+    let small_button_style = Style {
+        width: Val::Px(50.0),
+        ..button_style.clone()
+    };
+    let button_text_style = TextStyle {
+        font_size: 30.0,
+        color: TEXT_COLOR,
+        ..default()
+    };
+    let row_style = Style {
+        align_items: AlignItems::Center,
+        ..default()
+    };
+
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    width: Val::Percent(100.0),
+                    height: Val::Percent(100.0),
+                    align_items: AlignItems::Center,
+                    justify_content: JustifyContent::Center,
+                    ..default()
+                },
+                ..default()
+            },
+            OnArenaSettingsMenuScreen,
+        ))
+        .with_children(|parent| {
+            parent
+                .spawn(NodeBundle {
+                    style: Style {
+                        flex_direction: FlexDirection::Column,
+                        align_items: AlignItems::Center,
+                        padding: UiRect::all(Val::Px(10.0)),
+                        ..default()
+                    },
+                    background_color: bevy::color::palettes::css::CRIMSON.into(),
+                    ..default()
+                })
+                .with_children(|parent| {
+                    parent.spawn(TextBundle::from_section("Arena", button_text_style.clone()));
+                    parent
+                        .spawn(NodeBundle {
+                            style: row_style.clone(),
+                            ..default()
+                        })
+                        .with_children(|parent| {
+                            for (preset, text) in [
+                                (Arena::SMALL, "Small"),
+                                (Arena::MEDIUM, "Medium"),
+                                (Arena::LARGE, "Large"),
+                            ] {
+                                let mut entity = parent.spawn((
+                                    ButtonBundle {
+                                        style: button_style.clone(),
+                                        background_color: NORMAL_BUTTON.into(),
+                                        ..default()
+                                    },
+                                    preset,
+                                ));
+                                entity.with_children(|parent| {
+                                    parent.spawn(TextBundle::from_section(
+                                        text,
+                                        button_text_style.clone(),
+                                    ));
+                                });
+                                if *arena == preset {
+                                    entity.insert(SelectedOption);
+                                }
+                            }
+                        });
+
+                    // Custom size, one row per dimension
+                    parent.spawn((
+                        TextBundle::from_section(
+                            format!("{} x {}", arena.width, arena.height),
+                            button_text_style.clone(),
+                        ),
+                        ArenaSizeText,
+                    ));
+                    for (text, shrink, grow) in [
+                        (
+                            "Width",
+                            ArenaSizeButton::Width(-1),
+                            ArenaSizeButton::Width(1),
+                        ),
+                        (
+                            "Height",
+                            ArenaSizeButton::Height(-1),
+                            ArenaSizeButton::Height(1),
+                        ),
+                    ] {
+                        parent
+                            .spawn(NodeBundle {
+                                style: row_style.clone(),
+                                ..default()
+                            })
+                            .with_children(|parent| {
+                                parent.spawn(
+                                    TextBundle::from_section(text, button_text_style.clone())
+                                        .with_style(Style {
+                                            width: Val::Px(100.0),
+                                            ..default()
+                                        }),
+                                );
+                                for (button, label) in [(shrink, "-"), (grow, "+")] {
+                                    parent
+                                        .spawn((
+                                            ButtonBundle {
+                                                style: small_button_style.clone(),
+                                                background_color: NORMAL_BUTTON.into(),
+                                                ..default()
+                                            },
+                                            button,
+                                        ))
+                                        .with_children(|parent| {
+                                            parent.spawn(TextBundle::from_section(
+                                                label,
+                                                button_text_style.clone(),
+                                            ));
+                                        });
+                                }
+                            });
+                    }
+
+                    parent
+                        .spawn((
+                            ButtonBundle {
+                                style: button_style,
+                                background_color: NORMAL_BUTTON.into(),
+                                ..default()
+                            },
+                            MenuButtonAction::BackToSettings,
+                        ))
+                        .with_children(|parent| {
+                            parent.spawn(TextBundle::from_section("Back", button_text_style));
+                        });
+                });
+        });
+}
+
+fn arena_size_button(
+    interaction_query: Query<
+        (&Interaction, &ArenaSizeButton),
+        (Changed<Interaction>, With<Button>),
+    >,
+    mut presets: Query<(Entity, &Arena, &mut BackgroundColor), With<Button>>,
+    mut commands: Commands,
+    mut arena: ResMut<Arena>,
+) {
+    for (interaction, size_button) in &interaction_query {
+        if *interaction != Interaction::Pressed {
+            continue;
+        }
+
+        let resize = |size: u32, delta: i32| {
+            size.saturating_add_signed(delta)
+                .clamp(Arena::MIN_SIZE, Arena::MAX_SIZE)
+        };
+        match size_button {
+            ArenaSizeButton::Width(delta) => arena.width = resize(arena.width, *delta),
+            ArenaSizeButton::Height(delta) => arena.height = resize(arena.height, *delta),
+        }
+
+        // Highlight the preset matching the new size, if any
+        for (entity, preset, mut color) in &mut presets {
+            if *preset == *arena {
+                commands.entity(entity).insert(SelectedOption);
+                *color = PRESSED_BUTTON.into();
+            } else {
+                commands.entity(entity).remove::<SelectedOption>();
+                *color = NORMAL_BUTTON.into();
+            }
+        }
+    }
+}
+
+fn update_arena_size_text(arena: Res<Arena>, mut texts: Query<&mut Text, With<ArenaSizeText>>) {
+    if !arena.is_changed() {
+        return;
+    }
+
+    for mut text in &mut texts {
+        text.sections[0].value = format!("{} x {}", arena.width, arena.height);
+    }
+}
+
+fn settings_controls_menu_setup(mut commands: Commands, controls: Res<Controls>) {
+    commands.init_resource::<Rebinding>();
+    let button_style = Style {
+        width: Val::Px(110.0),
+        height: Val::Px(34.0),
+        margin: UiRect::all(Val::Px(3.0)),
+        justify_content: JustifyContent::Center,
+        align_items: AlignItems::Center,
+        ..default()
+    };
+    let button_text_style = TextStyle {
+        font_size: 18.0,
+        color: TEXT_COLOR,
+        ..default()
+    };
+    let label_text_style = TextStyle {
+        font_size: 22.0,
+        color: TEXT_COLOR,
+        ..default()
+    };
+
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    width: Val::Percent(100.0),
+                    height: Val::Percent(100.0),
+                    align_items: AlignItems::Center,
+                    justify_content: JustifyContent::Center,
+                    ..default()
+                },
+                ..default()
+            },
+            OnControlsSettingsMenuScreen,
+        ))
+        .with_children(|parent| {
+            parent
+                .spawn(NodeBundle {
+                    style: Style {
+                        flex_direction: FlexDirection::Column,
+                        align_items: AlignItems::Center,
+                        padding: UiRect::all(Val::Px(10.0)),
+                        ..default()
+                    },
+                    background_color: bevy::color::palettes::css::CRIMSON.into(),
+                    ..default()
+                })
+                .with_children(|parent| {
+                    parent.spawn(TextBundle::from_section(
+                        "Click a slot, then press a key or button, Escape cancels",
+                        button_text_style.clone(),
+                    ));
+
+                    // One row per action: two keys and a gamepad button
+                    let slots = (0..KEY_SLOTS)
+                        .map(BindingSlot::Key)
+                        .chain([BindingSlot::Button]);
+                    for action in Action::ALL {
+                        parent
+                            .spawn(NodeBundle {
+                                style: Style {
+                                    align_items: AlignItems::Center,
+                                    ..default()
+                                },
+                                ..default()
+                            })
+                            .with_children(|parent| {
+                                parent.spawn(
+                                    TextBundle::from_section(
+                                        action.name(),
+                                        label_text_style.clone(),
+                                    )
+                                    .with_style(Style {
+                                        width: Val::Px(90.0),
+                                        ..default()
+                                    }),
+                                );
+                                for slot in slots.clone() {
+                                    let binding = BindingButton { action, slot };
+                                    parent
+                                        .spawn((
+                                            ButtonBundle {
+                                                style: button_style.clone(),
+                                                background_color: NORMAL_BUTTON.into(),
+                                                ..default()
+                                            },
+                                            binding,
+                                        ))
+                                        .with_children(|parent| {
+                                            parent.spawn((
+                                                TextBundle::from_section(
+                                                    controls.bindings(action).slot_name(slot),
+                                                    button_text_style.clone(),
+                                                ),
+                                                BindingText(binding),
+                                            ));
+                                        });
+                                }
+                            });
+                    }
+
+                    parent
+                        .spawn(NodeBundle {
+                            style: Style {
+                                margin: UiRect::top(Val::Px(8.0)),
+                                ..default()
+                            },
+                            ..default()
+                        })
+                        .with_children(|parent| {
+                            for (action, text) in [
+                                (MenuButtonAction::ResetControls, "Reset"),
+                                (MenuButtonAction::BackToSettings, "Back"),
+                            ] {
+                                parent
+                                    .spawn((
+                                        ButtonBundle {
+                                            style: Style {
+                                                width: Val::Px(150.0),
+                                                height: Val::Px(45.0),
+                                                ..button_style.clone()
+                                            },
+                                            background_color: NORMAL_BUTTON.into(),
+                                            ..default()
+                                        },
+                                        action,
+                                    ))
+                                    .with_children(|parent| {
+                                        parent.spawn(TextBundle::from_section(
+                                            text,
+                                            label_text_style.clone(),
+                                        ));
+                                    });
+                            }
+                        });
+                });
+        });
+}
+
+fn binding_button(
+    interaction_query: Query<(&Interaction, &BindingButton), (Changed<Interaction>, With<Button>)>,
+    mut rebinding: ResMut<Rebinding>,
+) {
+    for (interaction, binding) in &interaction_query {
+        if *interaction == Interaction::Pressed {
+            rebinding.0 = Some(*binding);
+        }
+    }
+}
+
+fn not_rebinding(rebinding: Option<Res<Rebinding>>) -> bool {
+    rebinding.is_none_or(|rebinding| rebinding.0.is_none())
+}
+
+// Binds the first key or gamepad button pressed while a slot waits for one, Escape cancels
+fn capture_binding(
+    mut rebinding: ResMut<Rebinding>,
+    keys: Res<ButtonInput<KeyCode>>,
+    gamepad_buttons: Res<ButtonInput<GamepadButton>>,
+    mut controls: ResMut<Controls>,
+) {
+    let Some(BindingButton { action, slot }) = rebinding.0 else {
+        return;
+    };
+    // The key that picked the slot is not the one to bind
+    if rebinding.is_changed() {
+        return;
+    }
+
+    if keys.just_pressed(KeyCode::Escape) {
+        rebinding.0 = None;
+        return;
+    }
+    match slot {
+        BindingSlot::Key(index) => {
+            let Some(key) = keys.get_just_pressed().next() else {
+                return;
+            };
+            controls.bind_key(action, index, *key);
+        }
+        BindingSlot::Button => {
+            let Some(button) = gamepad_buttons.get_just_pressed().next() else {
+                return;
+            };
+            controls.bind_button(action, button.button_type);
+        }
+    }
+    rebinding.0 = None;
+}
+
+fn update_binding_texts(
+    controls: Res<Controls>,
+    rebinding: Res<Rebinding>,
+    mut texts: Query<(&mut Text, &BindingText)>,
+) {
+    if !controls.is_changed() && !rebinding.is_changed() {
+        return;
+    }
+
+    for (mut text, BindingText(binding)) in &mut texts {
+        text.sections[0].value = if rebinding.0 == Some(*binding) {
+            "...".to_string()
+        } else {
+            controls.bindings(binding.action).slot_name(binding.slot)
+        };
+    }
+}
+
+fn stop_rebinding(mut commands: Commands) {
+    commands.remove_resource::<Rebinding>();
+}
+
+fn menu_action(
+    interaction_query: Query<
+        (&Interaction, &MenuButtonAction),
+        (Changed<Interaction>, With<Button>),
+    >,
+    mut app_exit_events: EventWriter<AppExit>,
+    mut menu_state: ResMut<NextState<MenuState>>,
+    mut game_state: ResMut<NextState<GameState>>,
+    mut controls: ResMut<Controls>,
+    players: Res<Players>,
+) {
+    for (interaction, menu_button_action) in &interaction_query {
+        if *interaction == Interaction::Pressed {
+            match menu_button_action {
+                MenuButtonAction::Quit => {
+                    app_exit_events.send(AppExit::Success);
+                }
+                MenuButtonAction::NewGame => menu_state.set(MenuState::NewGame),
+                // Online games start once the other player is found
+                MenuButtonAction::Play if *players == Players::Online => {
+                    menu_state.set(MenuState::Lobby)
+                }
+                MenuButtonAction::Play => {
+                    game_state.set(GameState::Game);
+                    menu_state.set(MenuState::Disabled);
+                }
+                MenuButtonAction::EditLevel => {
+                    game_state.set(GameState::Editor);
+                    menu_state.set(MenuState::Disabled);
+                }
+                MenuButtonAction::Campaign => menu_state.set(MenuState::Campaign),
+                MenuButtonAction::Replays => menu_state.set(MenuState::Replays),
+                MenuButtonAction::HighScores => menu_state.set(MenuState::HighScores),
+                MenuButtonAction::Settings => menu_state.set(MenuState::Settings),
+                MenuButtonAction::SettingsSound => menu_state.set(MenuState::SettingsSound),
+                MenuButtonAction::SettingsArena => menu_state.set(MenuState::SettingsArena),
+                MenuButtonAction::SettingsControls => menu_state.set(MenuState::SettingsControls),
+                MenuButtonAction::ResetControls => *controls = Controls::default(),
+                MenuButtonAction::BackToMainMenu => menu_state.set(MenuState::Main),
+                MenuButtonAction::BackToSettings => menu_state.set(MenuState::Settings),
+            }
+        }
+    }
+}
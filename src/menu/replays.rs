@@ -0,0 +1,159 @@
+use std::path::PathBuf;
+
+use bevy::prelude::*;
+
+use super::{MenuButtonAction, MenuState, NORMAL_BUTTON};
+use crate::{
+    controls::{Action, BindingSlot, Controls},
+    game::replay::Playback,
+    sim::replay::{list_replays, Replay},
+    utils::{despawn_screen, GameState, TEXT_COLOR},
+};
+
+pub(super) fn replays_menu_plugin(app: &mut App) {
+    app.add_systems(OnEnter(MenuState::Replays), replays_menu_setup)
+        .add_systems(
+            Update,
+            replay_button_action.run_if(in_state(MenuState::Replays)),
+        )
+        .add_systems(
+            OnExit(MenuState::Replays),
+            despawn_screen::<OnReplaysMenuScreen>,
+        );
+}
+
+#[derive(Component)]
+struct OnReplaysMenuScreen;
+
+// Plays the saved replay at this path
+#[derive(Component)]
+struct ReplayButton(PathBuf);
+
+fn replays_menu_setup(mut commands: Commands, controls: Res<Controls>) {
+    let button_style = Style {
+        width: Val::Px(300.0),
+        height: Val::Px(45.0),
+        margin: UiRect::all(Val::Px(8.0)),
+        justify_content: JustifyContent::Center,
+        align_items: AlignItems::Center,
+        ..default()
+    };
+    let button_text_style = TextStyle {
+        font_size: 26.0,
+        color: TEXT_COLOR,
+        ..default()
+    };
+
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    width: Val::Percent(100.0),
+                    height: Val::Percent(100.0),
+                    align_items: AlignItems::Center,
+                    justify_content: JustifyContent::Center,
+                    ..default()
+                },
+                ..default()
+            },
+            OnReplaysMenuScreen,
+        ))
+        .with_children(|parent| {
+            parent
+                .spawn(NodeBundle {
+                    style: Style {
+                        flex_direction: FlexDirection::Column,
+                        align_items: AlignItems::Center,
+                        padding: UiRect::all(Val::Px(10.0)),
+                        ..default()
+                    },
+                    background_color: bevy::color::palettes::css::CRIMSON.into(),
+                    ..default()
+                })
+                .with_children(|parent| {
+                    // Only the most recent replays fit on the screen
+                    let replays = list_replays()
+                        .into_iter()
+                        .filter_map(|path| Replay::load(&path).ok().map(|replay| (path, replay)))
+                        .take(5)
+                        .collect::<Vec<_>>();
+                    if replays.is_empty() {
+                        parent.spawn(TextBundle::from_section(
+                            "No replays yet",
+                            button_text_style.clone(),
+                        ));
+                    }
+                    for (path, replay) in replays {
+                        parent
+                            .spawn((
+                                ButtonBundle {
+                                    style: button_style.clone(),
+                                    background_color: NORMAL_BUTTON.into(),
+                                    ..default()
+                                },
+                                ReplayButton(path),
+                            ))
+                            .with_children(|parent| {
+                                parent.spawn(TextBundle::from_section(
+                                    format!("Score {} - {} ticks", replay.score, replay.ticks),
+                                    button_text_style.clone(),
+                                ));
+                            });
+                    }
+
+                    // The replay follows the bindings, named after their first key
+                    let key = |action| controls.bindings(action).slot_name(BindingSlot::Key(0));
+                    parent.spawn(
+                        TextBundle::from_section(
+                            format!(
+                                "{}: pause  {}: fast-forward  {}: step",
+                                key(Action::Pause),
+                                key(Action::Confirm),
+                                key(Action::Right)
+                            ),
+                            TextStyle {
+                                font_size: 18.0,
+                                color: TEXT_COLOR,
+                                ..default()
+                            },
+                        )
+                        .with_style(Style {
+                            margin: UiRect::all(Val::Px(8.0)),
+                            ..default()
+                        }),
+                    );
+                    parent
+                        .spawn((
+                            ButtonBundle {
+                                style: button_style,
+                                background_color: NORMAL_BUTTON.into(),
+                                ..default()
+                            },
+                            MenuButtonAction::BackToMainMenu,
+                        ))
+                        .with_children(|parent| {
+                            parent.spawn(TextBundle::from_section("Back", button_text_style));
+                        });
+                });
+        });
+}
+
+fn replay_button_action(
+    interaction_query: Query<(&Interaction, &ReplayButton), (Changed<Interaction>, With<Button>)>,
+    mut commands: Commands,
+    mut menu_state: ResMut<NextState<MenuState>>,
+    mut game_state: ResMut<NextState<GameState>>,
+) {
+    for (interaction, ReplayButton(path)) in &interaction_query {
+        if *interaction == Interaction::Pressed {
+            match Replay::load(path) {
+                Ok(replay) => {
+                    commands.insert_resource(Playback::new(replay));
+                    game_state.set(GameState::Game);
+                    menu_state.set(MenuState::Disabled);
+                }
+                Err(err) => warn!("Could not load replay {}: {err}", path.display()),
+            }
+        }
+    }
+}
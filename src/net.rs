@@ -0,0 +1,561 @@
+use std::{
+    collections::BTreeMap,
+    io,
+    net::{SocketAddr, ToSocketAddrs, UdpSocket},
+    time::{Duration, Instant},
+};
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    sim::{
+        level::Level,
+        world::{Snake, SnakeWorld},
+    },
+    utils::{Direction, GameMode, GameState},
+};
+
+pub const DEFAULT_PORT: u16 = 7777;
+
+/// Ticks between a turn and the tick it applies to, the time it has to reach the other player.
+pub const INPUT_DELAY: u32 = 2;
+
+// Without a packet for this long the other player is considered gone
+const TIMEOUT: Duration = Duration::from_secs(5);
+// Greetings and inputs not acknowledged yet are sent again this often
+const RESEND_INTERVAL: Duration = Duration::from_millis(100);
+// Checksums are kept this many ticks, waiting for the other player's
+const CHECKSUM_HISTORY: u32 = 64;
+// Ticks a player can fall behind the clock before the game stops catching up
+const MAX_OWED_TICKS: u32 = 3;
+
+/// Datagrams exchanged by the two players, one JSON message each.
+#[derive(Serialize, Deserialize, Debug)]
+enum Message {
+    /// A guest asking to join.
+    Hello,
+    /// The host's answer, with everything needed to start the same game.
+    Welcome {
+        seed: u64,
+        mode: GameMode,
+        level_name: String,
+        // Sent whole so both players don't need the same level files
+        level: String,
+        input_delay: u32,
+    },
+    /// Every input of the sender from `first_tick` on that the other player has not acknowledged.
+    Inputs {
+        first_tick: u32,
+        directions: Vec<Direction>,
+        // Inputs of the other player received so far
+        ack: u32,
+        // The latest tick the sender ran and its checksum
+        checksum: Option<(u32, u64)>,
+    },
+    /// The sender left.
+    Bye,
+}
+
+fn send(socket: &UdpSocket, peer: SocketAddr, message: &Message) -> io::Result<()> {
+    let bytes = serde_json::to_vec(message)?;
+    socket.send_to(&bytes, peer)?;
+    Ok(())
+}
+
+// The next well formed message waiting on the socket, if any
+fn receive(socket: &UdpSocket) -> io::Result<Option<(Message, SocketAddr)>> {
+    let mut buffer = [0; 65536];
+    loop {
+        match socket.recv_from(&mut buffer) {
+            Ok((size, from)) => match serde_json::from_slice(&buffer[..size]) {
+                Ok(message) => return Ok(Some((message, from))),
+                Err(err) => warn!("Ignoring a malformed packet from {from}: {err}"),
+            },
+            Err(err) if err.kind() == io::ErrorKind::WouldBlock => return Ok(None),
+            // A previous packet could not be delivered, the other player may not be there yet
+            Err(err) if err.kind() == io::ErrorKind::ConnectionReset => {}
+            Err(err) => return Err(err),
+        }
+    }
+}
+
+/// Everything both players need to start the same game.
+#[derive(Clone, Debug)]
+pub struct GameSetup {
+    pub seed: u64,
+    pub mode: GameMode,
+    pub level: Level,
+    pub input_delay: u32,
+}
+
+/// A network game being set up on the lobby screen.
+#[derive(Resource)]
+pub struct Lobby {
+    socket: UdpSocket,
+    role: LobbyRole,
+    last_sent: Option<Instant>,
+}
+
+enum LobbyRole {
+    Host(GameSetup),
+    Guest(SocketAddr),
+}
+
+impl Lobby {
+    /// Waits for a player on this port, on every network interface.
+    ///
+    /// The socket takes IPv6 and, unless the system keeps IPv6 sockets apart, IPv4 players too.
+    /// Without IPv6 it falls back to IPv4 alone.
+    pub fn host(port: u16, setup: GameSetup) -> io::Result<Self> {
+        let socket =
+            UdpSocket::bind(("::", port)).or_else(|_| UdpSocket::bind(("0.0.0.0", port)))?;
+        socket.set_nonblocking(true)?;
+        Ok(Self {
+            socket,
+            role: LobbyRole::Host(setup),
+            last_sent: None,
+        })
+    }
+
+    /// Joins the player hosting at `host:port`, on the default port when there is none.
+    pub fn join(address: &str) -> io::Result<Self> {
+        let peer = if address.contains(':') {
+            address.to_socket_addrs()
+        } else {
+            (address, DEFAULT_PORT).to_socket_addrs()
+        }?
+        .next()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "unknown address"))?;
+        let any = if peer.is_ipv4() {
+            "0.0.0.0:0"
+        } else {
+            "[::]:0"
+        };
+        let socket = UdpSocket::bind(any)?;
+        socket.set_nonblocking(true)?;
+        Ok(Self {
+            socket,
+            role: LobbyRole::Guest(peer),
+            last_sent: None,
+        })
+    }
+
+    pub fn describe(&self) -> String {
+        match &self.role {
+            LobbyRole::Host(_) => match self.socket.local_addr() {
+                Ok(address) => format!("Waiting for a player on port {}", address.port()),
+                Err(_) => "Waiting for a player".to_string(),
+            },
+            LobbyRole::Guest(peer) => format!("Joining {peer}"),
+        }
+    }
+
+    /// Handles the packets received so far, returning the game once both players agree on it.
+    pub fn poll(&mut self) -> io::Result<Option<NetSession>> {
+        if let LobbyRole::Guest(peer) = self.role {
+            if self
+                .last_sent
+                .is_none_or(|sent| sent.elapsed() >= RESEND_INTERVAL)
+            {
+                send(&self.socket, peer, &Message::Hello)?;
+                self.last_sent = Some(Instant::now());
+            }
+        }
+
+        while let Some((message, from)) = receive(&self.socket)? {
+            match (&self.role, message) {
+                (LobbyRole::Host(setup), Message::Hello) => {
+                    send(&self.socket, from, &welcome(setup))?;
+                    info!("{from} joined the game");
+                    return Ok(Some(NetSession::new(
+                        self.socket.try_clone()?,
+                        from,
+                        0,
+                        setup.clone(),
+                    )));
+                }
+                (
+                    LobbyRole::Guest(peer),
+                    Message::Welcome {
+                        seed,
+                        mode,
+                        level_name,
+                        level,
+                        input_delay,
+                    },
+                ) if from == *peer => {
+                    let level = Level::parse(&level)
+                        .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+                    let setup = GameSetup {
+                        seed,
+                        mode,
+                        level: Level {
+                            name: level_name,
+                            ..level
+                        },
+                        input_delay,
+                    };
+                    return Ok(Some(NetSession::new(
+                        self.socket.try_clone()?,
+                        *peer,
+                        1,
+                        setup,
+                    )));
+                }
+                _ => {}
+            }
+        }
+        Ok(None)
+    }
+}
+
+fn welcome(setup: &GameSetup) -> Message {
+    Message::Welcome {
+        seed: setup.seed,
+        mode: setup.mode,
+        level_name: setup.level.name.clone(),
+        level: setup.level.encode(),
+        input_delay: setup.input_delay,
+    }
+}
+
+/// How a network game is going, anything but `Playing` ends it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum NetStatus {
+    Playing,
+    /// Both games stopped matching at this tick.
+    Desync(u32),
+    Left,
+    TimedOut,
+}
+
+impl NetStatus {
+    pub fn describe(self) -> String {
+        match self {
+            NetStatus::Playing => "Playing".to_string(),
+            NetStatus::Desync(tick) => format!("Games out of sync at tick {tick}"),
+            NetStatus::Left => "The other player left".to_string(),
+            NetStatus::TimedOut => "Lost the connection".to_string(),
+        }
+    }
+}
+
+/// A game played in lockstep with another machine: a tick only runs once the inputs of both players are known.
+///
+/// Turns apply `input_delay` ticks after they are made, so they usually arrive before they are needed.
+#[derive(Resource)]
+pub struct NetSession {
+    socket: UdpSocket,
+    peer: SocketAddr,
+    local_player: usize,
+    setup: GameSetup,
+    // Direction of each player at every tick known so far
+    local_inputs: Vec<Direction>,
+    remote_inputs: Vec<Direction>,
+    // Local inputs the other player has received
+    acked: u32,
+    tick: u32,
+    owed_ticks: u32,
+    checksums: BTreeMap<u32, u64>,
+    remote_checksums: BTreeMap<u32, u64>,
+    last_received: Instant,
+    last_sent: Instant,
+    status: NetStatus,
+}
+
+impl NetSession {
+    fn new(socket: UdpSocket, peer: SocketAddr, local_player: usize, setup: GameSetup) -> Self {
+        Self {
+            socket,
+            peer,
+            local_player,
+            setup,
+            local_inputs: Vec::new(),
+            remote_inputs: Vec::new(),
+            acked: 0,
+            tick: 0,
+            owed_ticks: 0,
+            checksums: BTreeMap::new(),
+            remote_checksums: BTreeMap::new(),
+            last_received: Instant::now(),
+            last_sent: Instant::now(),
+            status: NetStatus::Playing,
+        }
+    }
+
+    pub fn setup(&self) -> &GameSetup {
+        &self.setup
+    }
+
+    /// Index of the snake played on this machine, the host plays the first one.
+    pub fn local_player(&self) -> usize {
+        self.local_player
+    }
+
+    pub fn status(&self) -> NetStatus {
+        self.status
+    }
+
+    /// Fills the input delay with the starting directions, no one can turn before it is over.
+    pub fn start(&mut self, world: &SnakeWorld) {
+        let directions = world
+            .snakes()
+            .iter()
+            .map(Snake::direction)
+            .collect::<Vec<_>>();
+        let delay = self.setup.input_delay as usize;
+        self.local_inputs = vec![directions[self.local_player]; delay];
+        self.remote_inputs = vec![directions[1 - self.local_player]; delay];
+    }
+
+    /// Adds the ticks the clock asked for to the ones still waiting for inputs, returning how many are due.
+    pub fn add_ticks(&mut self, ticks: u32) -> u32 {
+        self.owed_ticks = (self.owed_ticks + ticks).min(MAX_OWED_TICKS);
+        self.owed_ticks
+    }
+
+    /// Whether the local turn for the tick `input_delay` ahead is still to be given.
+    pub fn needs_local_input(&self) -> bool {
+        self.local_inputs.len() as u32 <= self.tick + self.setup.input_delay
+    }
+
+    /// Schedules the local turn and sends it, without a turn the snake keeps its last direction.
+    pub fn push_local_input(&mut self, direction: Option<Direction>) {
+        let Some(direction) = direction.or(self.local_inputs.last().copied()) else {
+            return;
+        };
+        self.local_inputs.push(direction);
+        self.send_inputs();
+    }
+
+    /// Directions of both snakes for the next tick, once the other player's has arrived.
+    pub fn next_inputs(&mut self) -> Option<Vec<Direction>> {
+        let tick = self.tick as usize;
+        let local = *self.local_inputs.get(tick)?;
+        let remote = *self.remote_inputs.get(tick)?;
+        self.tick += 1;
+        self.owed_ticks = self.owed_ticks.saturating_sub(1);
+        Some(if self.local_player == 0 {
+            vec![local, remote]
+        } else {
+            vec![remote, local]
+        })
+    }
+
+    /// Remembers the state the last tick led to, to compare it with the other player's.
+    pub fn record_checksum(&mut self, checksum: u64) {
+        let tick = self.tick - 1;
+        self.checksums.insert(tick, checksum);
+        self.check(tick);
+        self.checksums = self
+            .checksums
+            .split_off(&tick.saturating_sub(CHECKSUM_HISTORY));
+    }
+
+    fn check(&mut self, tick: u32) {
+        if let (Some(local), Some(remote)) =
+            (self.checksums.get(&tick), self.remote_checksums.get(&tick))
+        {
+            if local != remote && self.status == NetStatus::Playing {
+                warn!("Desync at tick {tick}");
+                self.status = NetStatus::Desync(tick);
+            }
+        }
+    }
+
+    /// Reads the packets received so far and sends the inputs again when they may have been lost.
+    pub fn update(&mut self) {
+        loop {
+            match receive(&self.socket) {
+                Ok(Some((message, from))) if from == self.peer => {
+                    self.last_received = Instant::now();
+                    self.handle(message);
+                }
+                Ok(Some(_)) => {}
+                Ok(None) => break,
+                Err(err) => {
+                    warn!("Network error: {err}");
+                    break;
+                }
+            }
+        }
+
+        if self.status == NetStatus::Playing && self.last_received.elapsed() > TIMEOUT {
+            self.status = NetStatus::TimedOut;
+        }
+        if self.last_sent.elapsed() >= RESEND_INTERVAL {
+            self.send_inputs();
+        }
+    }
+
+    fn handle(&mut self, message: Message) {
+        match message {
+            // The welcome was lost, the guest is still asking
+            Message::Hello if self.local_player == 0 => {
+                if let Err(err) = send(&self.socket, self.peer, &welcome(&self.setup)) {
+                    warn!("Could not welcome the other player: {err}");
+                }
+            }
+            Message::Inputs {
+                first_tick,
+                directions,
+                ack,
+                checksum,
+            } => {
+                self.acked = self.acked.max(ack);
+                let known = self.remote_inputs.len() as u32;
+                // Packets only repeat what is already known or carry on from it
+                if first_tick <= known {
+                    self.remote_inputs
+                        .extend(directions.into_iter().skip((known - first_tick) as usize));
+                }
+                if let Some((tick, checksum)) = checksum {
+                    self.remote_checksums.insert(tick, checksum);
+                    self.check(tick);
+                    self.remote_checksums = self
+                        .remote_checksums
+                        .split_off(&tick.saturating_sub(CHECKSUM_HISTORY));
+                }
+            }
+            Message::Bye => self.status = NetStatus::Left,
+            Message::Hello | Message::Welcome { .. } => {}
+        }
+    }
+
+    fn send_inputs(&mut self) {
+        let first_tick = self.acked.min(self.local_inputs.len() as u32);
+        let message = Message::Inputs {
+            first_tick,
+            directions: self.local_inputs[first_tick as usize..].to_vec(),
+            ack: self.remote_inputs.len() as u32,
+            checksum: self
+                .checksums
+                .last_key_value()
+                .map(|(tick, sum)| (*tick, *sum)),
+        };
+        if let Err(err) = send(&self.socket, self.peer, &message) {
+            warn!("Could not send inputs: {err}");
+        }
+        self.last_sent = Instant::now();
+    }
+}
+
+impl Drop for NetSession {
+    // Lets the other player know right away instead of waiting for the timeout
+    fn drop(&mut self) {
+        let _ = send(&self.socket, self.peer, &Message::Bye);
+    }
+}
+
+pub fn net_plugin(app: &mut App) {
+    app.add_systems(
+        Update,
+        update_session.run_if(in_state(GameState::Game).and_then(resource_exists::<NetSession>)),
+    );
+}
+
+// A lost connection or a desync ends the game for both players
+fn update_session(mut session: ResMut<NetSession>, mut game_state: ResMut<NextState<GameState>>) {
+    session.update();
+    if session.status() != NetStatus::Playing {
+        game_state.set(GameState::GameOver);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+
+    use super::*;
+    use crate::utils::Arena;
+
+    fn setup() -> GameSetup {
+        GameSetup {
+            seed: 7,
+            mode: GameMode::Classic,
+            level: Level::open(Arena::SMALL),
+            input_delay: INPUT_DELAY,
+        }
+    }
+
+    // Polls until there is an answer, the packets never leave this machine
+    fn wait_for<T>(mut poll: impl FnMut() -> Option<T>) -> T {
+        let deadline = Instant::now() + Duration::from_secs(2);
+        loop {
+            if let Some(value) = poll() {
+                return value;
+            }
+            assert!(Instant::now() < deadline, "no answer");
+            thread::sleep(Duration::from_millis(1));
+        }
+    }
+
+    // A host and a guest in the same process, both started on the same world
+    fn connect() -> (NetSession, NetSession, SnakeWorld) {
+        let mut host_lobby = Lobby::host(0, setup()).unwrap();
+        let port = host_lobby.socket.local_addr().unwrap().port();
+        let mut guest_lobby = Lobby::join(&format!("127.0.0.1:{port}")).unwrap();
+        assert!(guest_lobby.poll().unwrap().is_none());
+        let mut host = wait_for(|| host_lobby.poll().unwrap());
+        let mut guest = wait_for(|| guest_lobby.poll().unwrap());
+        assert_eq!(guest.setup().seed, 7);
+        assert_eq!(guest.setup().level.arena, Arena::SMALL);
+
+        let world = SnakeWorld::from_level(&guest.setup().level).with_rival();
+        host.start(&world);
+        guest.start(&world);
+        (host, guest, world)
+    }
+
+    #[test]
+    fn turns_reach_the_other_player_after_the_input_delay() {
+        let (mut host, mut guest, world) = connect();
+        assert_eq!((host.local_player(), guest.local_player()), (0, 1));
+        let starting = world
+            .snakes()
+            .iter()
+            .map(Snake::direction)
+            .collect::<Vec<_>>();
+
+        host.push_local_input(Some(Direction::Left));
+        guest.push_local_input(Some(Direction::Right));
+        // The ticks of the delay run on the starting directions without waiting
+        for _ in 0..INPUT_DELAY {
+            assert_eq!(host.next_inputs(), Some(starting.clone()));
+            assert_eq!(guest.next_inputs(), Some(starting.clone()));
+        }
+        assert_eq!(host.next_inputs(), None);
+
+        let turns = vec![Direction::Left, Direction::Right];
+        let host_inputs = wait_for(|| {
+            host.update();
+            host.next_inputs()
+        });
+        let guest_inputs = wait_for(|| {
+            guest.update();
+            guest.next_inputs()
+        });
+        assert_eq!(host_inputs, turns);
+        assert_eq!(guest_inputs, turns);
+        assert_eq!(host.status(), NetStatus::Playing);
+    }
+
+    #[test]
+    fn different_checksums_are_a_desync() {
+        let (mut host, mut guest, _) = connect();
+        host.next_inputs().unwrap();
+        guest.next_inputs().unwrap();
+        host.record_checksum(1);
+        guest.record_checksum(2);
+        // Inputs carry the checksum of the last tick
+        host.push_local_input(None);
+        guest.push_local_input(None);
+
+        let status = wait_for(|| {
+            host.update();
+            Some(host.status()).filter(|status| *status != NetStatus::Playing)
+        });
+        assert_eq!(status, NetStatus::Desync(0));
+    }
+}
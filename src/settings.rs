@@ -0,0 +1,176 @@
+use std::{fs, io, path::PathBuf};
+
+use bevy::{prelude::*, window::PrimaryWindow};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    controls::Controls,
+    storage::data_dir,
+    utils::{Arena, GameMode, MusicVolume, Players, SelectedLevel, SfxVolume, Theme, Volume},
+};
+
+/// Bumped whenever a field changes meaning, see `Settings::migrate`.
+const SETTINGS_VERSION: u32 = 2;
+
+/// Player preferences saved between runs.
+///
+/// Fields missing from an older file take their default value, so adding one keeps old files loading.
+#[derive(Resource, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    pub version: u32,
+    // Master volume, the other two are scaled by it
+    pub volume: u32,
+    pub sfx_volume: u32,
+    pub music_volume: u32,
+    pub mode: GameMode,
+    pub players: Players,
+    pub arena: Arena,
+    // Name of a file in the levels folder
+    pub level: Option<String>,
+    pub controls: Controls,
+    pub theme: Theme,
+    // Logical size of the window when the settings were last saved
+    pub window_width: f32,
+    pub window_height: f32,
+    // Only ever set by editing the file, `--seed` takes precedence
+    pub seed: Option<u64>,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            version: SETTINGS_VERSION,
+            volume: 7,
+            sfx_volume: 9,
+            music_volume: 6,
+            mode: GameMode::default(),
+            players: Players::default(),
+            arena: Arena::default(),
+            level: None,
+            controls: Controls::default(),
+            theme: Theme::default(),
+            window_width: 500.,
+            window_height: 500.,
+            seed: None,
+        }
+    }
+}
+
+fn settings_path() -> PathBuf {
+    data_dir().join("settings.json")
+}
+
+impl Settings {
+    pub fn load() -> Self {
+        let Ok(text) = fs::read_to_string(settings_path()) else {
+            return Self::default();
+        };
+        match serde_json::from_str::<Settings>(&text) {
+            Ok(settings) => settings.migrate(),
+            Err(err) => {
+                warn!("Ignoring unreadable settings: {err}");
+                Self::default()
+            }
+        }
+    }
+
+    pub fn save(&self) -> io::Result<()> {
+        fs::create_dir_all(data_dir())?;
+        fs::write(settings_path(), serde_json::to_string_pretty(self)?)
+    }
+
+    // Upgrades values saved by an older version of the game
+    fn migrate(mut self) -> Self {
+        // Version 1 always had a dark title bar, only new players follow the system theme
+        if self.version < 2 {
+            self.theme = Theme::Dark;
+        }
+        self.version = SETTINGS_VERSION;
+        self
+    }
+}
+
+/// Shows a theme picked in the menu on the window right away.
+pub fn apply_theme(theme: Res<Theme>, mut windows: Query<&mut Window, With<PrimaryWindow>>) {
+    if !theme.is_changed() {
+        return;
+    }
+    for mut window in &mut windows {
+        window.window_theme = theme.window_theme();
+    }
+}
+
+/// Writes the settings edited in the menu to disk, run when leaving a settings screen.
+pub fn save_settings(
+    volume: Res<Volume>,
+    sfx_volume: Res<SfxVolume>,
+    music_volume: Res<MusicVolume>,
+    mode: Res<GameMode>,
+    players: Res<Players>,
+    arena: Res<Arena>,
+    level: Res<SelectedLevel>,
+    controls: Res<Controls>,
+    theme: Res<Theme>,
+    windows: Query<&Window, With<PrimaryWindow>>,
+    mut settings: ResMut<Settings>,
+) {
+    // Resizing has no screen of its own, the size is kept along with the other settings
+    let (window_width, window_height) = windows
+        .get_single()
+        .map_or((settings.window_width, settings.window_height), |window| {
+            (window.width(), window.height())
+        });
+    let edited = Settings {
+        volume: volume.0,
+        sfx_volume: sfx_volume.0,
+        music_volume: music_volume.0,
+        mode: *mode,
+        players: *players,
+        arena: *arena,
+        level: level.0.clone(),
+        controls: controls.clone(),
+        theme: *theme,
+        window_width,
+        window_height,
+        ..settings.clone()
+    };
+    if edited == *settings {
+        return;
+    }
+
+    *settings = edited;
+    if let Err(err) = settings.save() {
+        warn!("Could not save settings: {err}");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn version_1_files_keep_the_dark_theme() {
+        let settings = serde_json::from_str::<Settings>(r#"{"version": 1, "volume": 3}"#)
+            .unwrap()
+            .migrate();
+        assert_eq!(settings.version, SETTINGS_VERSION);
+        assert_eq!(settings.volume, 3);
+        assert_eq!(settings.theme, Theme::Dark);
+        assert_eq!(Settings::default().theme, Theme::System);
+    }
+
+    #[test]
+    fn current_files_load_unchanged() {
+        let settings = Settings {
+            theme: Theme::Light,
+            window_width: 640.,
+            ..Settings::default()
+        };
+        let text = serde_json::to_string(&settings).unwrap();
+        assert_eq!(
+            serde_json::from_str::<Settings>(&text).unwrap().migrate(),
+            settings
+        );
+    }
+}
@@ -0,0 +1,366 @@
+use std::collections::VecDeque;
+
+use super::{
+    board::{Arena, Direction, Food, GameMode, Position},
+    level::Level,
+    world::SnakeWorld,
+};
+
+/// Drives a snake instead of the keyboard: asked for a direction before every tick.
+///
+/// The world is only read, the direction is applied with the inputs of the other snakes.
+pub trait SnakeController: Send + Sync {
+    fn next_direction(&mut self, world: &SnakeWorld, player: usize) -> Direction;
+}
+
+/// The computer players, from the weakest to the one that fills the boards it supports.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Strategy {
+    /// Heads straight for the closest food, even into a dead end.
+    Greedy,
+    /// Follows the shortest path to food, looks for the most room when there is none.
+    Bfs,
+    /// Goes around a cycle through every cell, slow but fills the whole board when it plays alone
+    /// on a board it supports, see `supports`.
+    Hamiltonian,
+}
+
+impl Strategy {
+    pub const ALL: [Strategy; 3] = [Strategy::Greedy, Strategy::Bfs, Strategy::Hamiltonian];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Strategy::Greedy => "Greedy",
+            Strategy::Bfs => "BFS",
+            Strategy::Hamiltonian => "Hamiltonian",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|strategy| strategy.name().eq_ignore_ascii_case(name))
+    }
+
+    /// Whether the strategy plays as described with that many snakes on this level.
+    ///
+    /// The Hamiltonian player needs an open board with an even number of cells all to itself,
+    /// anywhere else it plays like `Bfs` and dies long before filling the board.
+    pub fn supports(self, level: &Level, players: usize) -> bool {
+        match self {
+            Strategy::Greedy | Strategy::Bfs => true,
+            Strategy::Hamiltonian => {
+                players == 1
+                    && level.obstacles.is_empty()
+                    && hamiltonian_cycle(level.arena).is_some()
+            }
+        }
+    }
+
+    pub fn controller(self) -> Box<dyn SnakeController> {
+        match self {
+            Strategy::Greedy => Box::new(Greedy),
+            Strategy::Bfs => Box::new(Bfs),
+            Strategy::Hamiltonian => Box::new(Hamiltonian::default()),
+        }
+    }
+}
+
+pub struct Greedy;
+
+impl SnakeController for Greedy {
+    fn next_direction(&mut self, world: &SnakeWorld, player: usize) -> Direction {
+        let snake = &world.snakes()[player];
+        let targets = grow_food(world);
+        let safe = safe_directions(world, player);
+        // Without food in sight the snake keeps going while it can
+        safe.iter()
+            .copied()
+            .min_by_key(|direction| {
+                let next = world.next_position(snake.head(), *direction);
+                let distance = targets
+                    .iter()
+                    .map(|target| distance(world, next, *target))
+                    .min()
+                    .unwrap_or(0);
+                (distance, *direction != snake.direction())
+            })
+            .unwrap_or(snake.direction())
+    }
+}
+
+pub struct Bfs;
+
+impl SnakeController for Bfs {
+    fn next_direction(&mut self, world: &SnakeWorld, player: usize) -> Direction {
+        let snake = &world.snakes()[player];
+        let safe = safe_directions(world, player);
+        let blocked = blocked_cells(world);
+        let targets = grow_food(world);
+
+        // Breadth first from every safe first move at once, so the first food reached tells which move to make
+        let arena = world.arena();
+        let mut seen = blocked.clone();
+        let mut queue = VecDeque::new();
+        for direction in &safe {
+            let next = world.next_position(snake.head(), *direction);
+            seen[cell_index(arena, next)] = true;
+            queue.push_back((next, *direction));
+        }
+        while let Some((position, first)) = queue.pop_front() {
+            if targets.contains(&position) {
+                return first;
+            }
+            for direction in Direction::ALL {
+                let next = world.next_position(position, direction);
+                if arena.contains(next) && !seen[cell_index(arena, next)] {
+                    seen[cell_index(arena, next)] = true;
+                    queue.push_back((next, first));
+                }
+            }
+        }
+
+        // No way to food: stay alive in the largest open area
+        safe.iter()
+            .copied()
+            .max_by_key(|direction| {
+                let next = world.next_position(snake.head(), *direction);
+                (
+                    reachable_cells(world, &blocked, next),
+                    *direction == snake.direction(),
+                )
+            })
+            .unwrap_or(snake.direction())
+    }
+}
+
+/// Follows a cycle through every cell of the board, falling back to `Bfs` where there is none.
+///
+/// Boards with obstacles or an odd number of cells have no such cycle, `Strategy::supports`
+/// keeps it off them. Another snake in the way of the cycle also makes it fall back.
+#[derive(Default)]
+pub struct Hamiltonian {
+    // Next and previous cell of every cell along the cycle, built for this board
+    cycle: Option<(Arena, Vec<Position>, Vec<Position>)>,
+}
+
+impl SnakeController for Hamiltonian {
+    fn next_direction(&mut self, world: &SnakeWorld, player: usize) -> Direction {
+        let arena = world.arena();
+        if !world.obstacles().is_empty() {
+            return Bfs.next_direction(world, player);
+        }
+        if self
+            .cycle
+            .as_ref()
+            .is_none_or(|(cycle_arena, _, _)| *cycle_arena != arena)
+        {
+            self.cycle = hamiltonian_cycle(arena).map(|cycle| {
+                let mut next = vec![Position { x: 0, y: 0 }; cycle.len()];
+                let mut previous = next.clone();
+                for (index, position) in cycle.iter().enumerate() {
+                    let following = cycle[(index + 1) % cycle.len()];
+                    next[cell_index(arena, *position)] = following;
+                    previous[cell_index(arena, following)] = *position;
+                }
+                (arena, next, previous)
+            });
+        }
+        let Some((_, next, previous)) = &self.cycle else {
+            return Bfs.next_direction(world, player);
+        };
+
+        // The snake goes around whichever way it is heading, a turn back would be ignored anyway
+        let snake = &world.snakes()[player];
+        let head = snake.head();
+        let towards = |target: Position| {
+            Direction::ALL
+                .into_iter()
+                .find(|direction| world.next_position(head, *direction) == target)
+        };
+        let mut direction = towards(next[cell_index(arena, head)]);
+        if direction == Some(snake.direction().opposite()) {
+            direction = towards(previous[cell_index(arena, head)]);
+        }
+        match direction {
+            // Another snake may be in the way of the cycle
+            Some(direction) if safe_directions(world, player).contains(&direction) => direction,
+            _ => Bfs.next_direction(world, player),
+        }
+    }
+}
+
+// Row by row with the first column left for the way back, so the board needs an even number of rows
+fn hamiltonian_cycle(arena: Arena) -> Option<Vec<Position>> {
+    if !arena.height.is_multiple_of(2) {
+        if !arena.width.is_multiple_of(2) {
+            return None;
+        }
+        // Going column by column works the same when the columns are even
+        let transposed = hamiltonian_cycle(Arena::new(arena.height, arena.width))?;
+        return Some(
+            transposed
+                .into_iter()
+                .map(|position| Position {
+                    x: position.y,
+                    y: position.x,
+                })
+                .collect(),
+        );
+    }
+
+    let (width, height) = (arena.width as i32, arena.height as i32);
+    let mut cycle = vec![Position { x: 0, y: 0 }];
+    for y in 0..height {
+        let row = (1..width).map(|x| Position { x, y });
+        if y % 2 == 0 {
+            cycle.extend(row);
+        } else {
+            cycle.extend(row.rev());
+        }
+    }
+    cycle.extend((1..height).rev().map(|y| Position { x: 0, y }));
+    Some(cycle)
+}
+
+fn cell_index(arena: Arena, position: Position) -> usize {
+    (position.y * arena.width as i32 + position.x) as usize
+}
+
+fn grow_food(world: &SnakeWorld) -> Vec<Position> {
+    world
+        .food()
+        .iter()
+        .filter(|(_, food)| *food == Food::Grow)
+        .map(|(position, _)| *position)
+        .collect()
+}
+
+// Steps between two cells, shorter across the edges in wrap mode
+fn distance(world: &SnakeWorld, from: Position, to: Position) -> u32 {
+    let arena = world.arena();
+    let dx = from.x.abs_diff(to.x);
+    let dy = from.y.abs_diff(to.y);
+    match world.mode() {
+        GameMode::Classic => dx + dy,
+        GameMode::Wrap => dx.min(arena.width - dx) + dy.min(arena.height - dy),
+    }
+}
+
+// Cells a head dies moving into, see `SnakeWorld::is_deadly`: tails don't move out of the way in time
+fn blocked_cells(world: &SnakeWorld) -> Vec<bool> {
+    let arena = world.arena();
+    let mut blocked = vec![false; (arena.width * arena.height) as usize];
+    let segments = world.snakes().iter().flat_map(|snake| snake.segments());
+    for position in world.obstacles().iter().chain(segments) {
+        blocked[cell_index(arena, *position)] = true;
+    }
+    blocked
+}
+
+// Moves that don't end the game right away, without guessing where the other snakes go
+fn safe_directions(world: &SnakeWorld, player: usize) -> Vec<Direction> {
+    let snake = &world.snakes()[player];
+    Direction::ALL
+        .into_iter()
+        .filter(|direction| *direction != snake.direction().opposite())
+        .filter(|direction| !world.is_deadly(world.next_position(snake.head(), *direction)))
+        .collect()
+}
+
+fn reachable_cells(world: &SnakeWorld, blocked: &[bool], start: Position) -> usize {
+    let arena = world.arena();
+    let mut seen = blocked.to_vec();
+    seen[cell_index(arena, start)] = true;
+    let mut queue = VecDeque::from([start]);
+    let mut count = 0;
+    while let Some(position) = queue.pop_front() {
+        count += 1;
+        for direction in Direction::ALL {
+            let next = world.next_position(position, direction);
+            if arena.contains(next) && !seen[cell_index(arena, next)] {
+                seen[cell_index(arena, next)] = true;
+                queue.push_back(next);
+            }
+        }
+    }
+    count
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{rngs::StdRng, SeedableRng};
+
+    use super::*;
+
+    fn at(x: i32, y: i32) -> Position {
+        Position { x, y }
+    }
+
+    // Plays a lone snake until the game ends, or gives up after a very long game
+    fn play(strategy: Strategy, world: SnakeWorld, seed: u64) -> SnakeWorld {
+        let mut world = world;
+        let mut controller = strategy.controller();
+        let mut rng = StdRng::seed_from_u64(seed);
+        while !world.is_over() && world.tick() < 100_000 {
+            let direction = controller.next_direction(&world, 0);
+            world.step(direction, &mut rng);
+        }
+        world
+    }
+
+    #[test]
+    fn hamiltonian_fills_the_boards_it_supports() {
+        let arenas = [
+            Arena::new(5, 6),
+            Arena::new(6, 5),
+            Arena::new(6, 6),
+            Arena::new(7, 8),
+            Arena::SMALL,
+        ];
+        for arena in arenas {
+            assert!(Strategy::Hamiltonian.supports(&Level::open(arena), 1));
+            for mode in GameMode::ALL {
+                for seed in 0..3 {
+                    let world = play(
+                        Strategy::Hamiltonian,
+                        SnakeWorld::new(arena).with_mode(mode),
+                        seed,
+                    );
+                    assert!(
+                        world.is_won(),
+                        "{arena:?} {mode:?} seed {seed}: {:?}",
+                        world.death()
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn hamiltonian_does_not_support_boards_without_a_cycle() {
+        for arena in [Arena::new(5, 5), Arena::new(7, 7), Arena::new(5, 7)] {
+            assert!(!Strategy::Hamiltonian.supports(&Level::open(arena), 1));
+        }
+        let mut level = Level::open(Arena::SMALL);
+        level.obstacles.push(at(5, 5));
+        assert!(!Strategy::Hamiltonian.supports(&level, 1));
+        assert!(!Strategy::Hamiltonian.supports(&Level::open(Arena::SMALL), 2));
+        assert!(Strategy::Bfs.supports(&level, 2));
+    }
+
+    #[test]
+    fn the_tail_is_not_a_safe_move() {
+        let world = SnakeWorld::new(Arena::SMALL).arranged(
+            &[at(3, 3), at(3, 2), at(4, 2), at(4, 3)],
+            Direction::Up,
+            &[(at(5, 3), Food::Grow)],
+        );
+        assert_eq!(safe_directions(&world, 0), [Direction::Left, Direction::Up]);
+        // The food right behind the tail doesn't lure them into it
+        for strategy in Strategy::ALL {
+            let direction = strategy.controller().next_direction(&world, 0);
+            assert_ne!(direction, Direction::Right, "{}", strategy.name());
+        }
+    }
+}
@@ -0,0 +1,112 @@
+#[cfg(feature = "bevy")]
+use bevy::prelude::{Component, Resource};
+use serde::{Deserialize, Serialize};
+
+/// Size of the board in cells, picked before a game starts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "bevy", derive(Resource, Component))]
+pub struct Arena {
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Arena {
+    pub const SMALL: Arena = Arena::new(10, 10);
+    pub const MEDIUM: Arena = Arena::new(16, 16);
+    pub const LARGE: Arena = Arena::new(24, 24);
+
+    pub const MIN_SIZE: u32 = 5;
+    pub const MAX_SIZE: u32 = 30;
+
+    pub const fn new(width: u32, height: u32) -> Self {
+        Self { width, height }
+    }
+
+    pub fn contains(&self, position: Position) -> bool {
+        position.x >= 0
+            && position.y >= 0
+            && (position.x as u32) < self.width
+            && (position.y as u32) < self.height
+    }
+
+    /// Brings a position off the board back in from the opposite edge.
+    pub fn wrap(&self, position: Position) -> Position {
+        Position {
+            x: position.x.rem_euclid(self.width as i32),
+            y: position.y.rem_euclid(self.height as i32),
+        }
+    }
+}
+
+impl Default for Arena {
+    fn default() -> Self {
+        Self::SMALL
+    }
+}
+
+/// Rules of a game, picked in the menu before it starts.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "bevy", derive(Resource, Component))]
+pub enum GameMode {
+    #[default]
+    Classic,
+    /// No walls, leaving the board brings the snake back on the opposite edge.
+    Wrap,
+}
+
+impl GameMode {
+    pub const ALL: [GameMode; 2] = [GameMode::Classic, GameMode::Wrap];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            GameMode::Classic => "Classic",
+            GameMode::Wrap => "Wrap",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|mode| mode.name().eq_ignore_ascii_case(name))
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "bevy", derive(Component))]
+pub struct Position {
+    pub x: i32,
+    pub y: i32,
+}
+
+#[derive(PartialEq, Eq, Copy, Clone, Debug, Serialize, Deserialize)]
+pub enum Direction {
+    Left,
+    Up,
+    Right,
+    Down,
+}
+
+impl Direction {
+    pub const ALL: [Direction; 4] = [
+        Direction::Left,
+        Direction::Up,
+        Direction::Right,
+        Direction::Down,
+    ];
+
+    pub fn opposite(self) -> Self {
+        match self {
+            Self::Left => Self::Right,
+            Self::Right => Self::Left,
+            Self::Up => Self::Down,
+            Self::Down => Self::Up,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[cfg_attr(feature = "bevy", derive(Component))]
+pub enum Food {
+    Grow,
+    Shrink,
+}
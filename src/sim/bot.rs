@@ -0,0 +1,483 @@
+use std::{
+    fmt,
+    io::{self, BufRead, BufReader, Read, Write},
+    net::{TcpListener, TcpStream},
+    time::{Duration, Instant},
+};
+#[cfg(unix)]
+use std::{
+    fs,
+    os::unix::{
+        fs::FileTypeExt,
+        net::{UnixListener, UnixStream},
+    },
+    path::PathBuf,
+};
+
+use serde::{Deserialize, Serialize};
+
+use super::{
+    ai::SnakeController,
+    board::{Arena, Direction, Food, GameMode, Position},
+    world::SnakeWorld,
+};
+
+/// Time a bot has to answer a state before its snake keeps going straight.
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_millis(1000);
+
+// Answers are a few bytes, a bot sending lines this long without an end is broken
+const MAX_LINE_LENGTH: usize = 4096;
+
+/// Where the game waits for a bot to connect.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum BotAddress {
+    Tcp(String),
+    #[cfg(unix)]
+    Unix(PathBuf),
+}
+
+impl BotAddress {
+    /// Reads `unix:<path>`, `tcp:<host>:<port>` or a bare `<host>:<port>`.
+    pub fn parse(value: &str) -> Option<Self> {
+        #[cfg(unix)]
+        if let Some(path) = value.strip_prefix("unix:") {
+            return (!path.is_empty()).then(|| BotAddress::Unix(PathBuf::from(path)));
+        }
+        let address = value.strip_prefix("tcp:").unwrap_or(value);
+        address
+            .contains(':')
+            .then(|| BotAddress::Tcp(address.to_string()))
+    }
+}
+
+impl fmt::Display for BotAddress {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            BotAddress::Tcp(address) => write!(f, "tcp:{address}"),
+            #[cfg(unix)]
+            BotAddress::Unix(path) => write!(f, "unix:{}", path.display()),
+        }
+    }
+}
+
+/// Lines sent to the bot, one JSON object each.
+#[derive(Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum ServerMessage<'a> {
+    /// The board before a tick, answered with the direction of the bot's snake.
+    State {
+        tick: u32,
+        // Index of the bot's snake in `snakes`
+        player: usize,
+        mode: GameMode,
+        arena: Arena,
+        obstacles: &'a [Position],
+        food: Vec<FoodState>,
+        snakes: Vec<SnakeState<'a>>,
+    },
+    /// The game is over, nothing more is sent.
+    End {
+        tick: u32,
+        score: u32,
+        won: bool,
+        death: Option<&'static str>,
+        winner: Option<usize>,
+    },
+}
+
+#[derive(Serialize)]
+struct FoodState {
+    x: i32,
+    y: i32,
+    kind: Food,
+}
+
+#[derive(Serialize)]
+struct SnakeState<'a> {
+    // Head first
+    segments: &'a [Position],
+    direction: Direction,
+    score: u32,
+    alive: bool,
+}
+
+/// A bot's answer to a state.
+#[derive(Deserialize)]
+struct Reply {
+    direction: Direction,
+    // Tick of the state answered, so late answers to a state that timed out are told apart
+    tick: Option<u32>,
+}
+
+enum Connection {
+    Tcp(TcpStream),
+    #[cfg(unix)]
+    Unix(UnixStream),
+}
+
+impl Connection {
+    fn try_clone(&self) -> io::Result<Self> {
+        Ok(match self {
+            Connection::Tcp(stream) => Connection::Tcp(stream.try_clone()?),
+            #[cfg(unix)]
+            Connection::Unix(stream) => Connection::Unix(stream.try_clone()?),
+        })
+    }
+
+    fn set_read_timeout(&self, timeout: Duration) -> io::Result<()> {
+        match self {
+            Connection::Tcp(stream) => stream.set_read_timeout(Some(timeout)),
+            #[cfg(unix)]
+            Connection::Unix(stream) => stream.set_read_timeout(Some(timeout)),
+        }
+    }
+}
+
+impl io::Read for Connection {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self {
+            Connection::Tcp(stream) => stream.read(buf),
+            #[cfg(unix)]
+            Connection::Unix(stream) => stream.read(buf),
+        }
+    }
+}
+
+impl Write for Connection {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            Connection::Tcp(stream) => stream.write(buf),
+            #[cfg(unix)]
+            Connection::Unix(stream) => stream.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            Connection::Tcp(stream) => stream.flush(),
+            #[cfg(unix)]
+            Connection::Unix(stream) => stream.flush(),
+        }
+    }
+}
+
+/// Lets a program outside the game drive a snake over a JSON lines socket.
+///
+/// Before every tick the bot gets a `state` line and answers with `{"direction": "Up"}`.
+/// Without an answer in time the snake keeps its direction and the game goes on.
+pub struct BotController {
+    reader: BufReader<Connection>,
+    writer: Connection,
+    timeout: Duration,
+    // Bytes of an answer still arriving when the last state timed out
+    pending: Vec<u8>,
+    // Lines that could not be read, until the caller takes them
+    malformed: Vec<serde_json::Error>,
+    timeouts: u32,
+    disconnected: bool,
+    #[cfg(unix)]
+    socket_path: Option<PathBuf>,
+}
+
+impl BotController {
+    /// Waits for a bot to connect on this address.
+    pub fn listen(address: &BotAddress, timeout: Duration) -> io::Result<Self> {
+        let connection = match address {
+            BotAddress::Tcp(address) => {
+                let (stream, _) = TcpListener::bind(address)?.accept()?;
+                // States are small and each one is waited for
+                stream.set_nodelay(true)?;
+                Connection::Tcp(stream)
+            }
+            #[cfg(unix)]
+            BotAddress::Unix(path) => {
+                // A socket left behind by a previous run, any other file is not ours to remove
+                match fs::symlink_metadata(path) {
+                    Ok(metadata) if metadata.file_type().is_socket() => fs::remove_file(path)?,
+                    Ok(_) => {
+                        return Err(io::Error::new(
+                            io::ErrorKind::AlreadyExists,
+                            format!("{} is not a socket", path.display()),
+                        ))
+                    }
+                    Err(err) if err.kind() == io::ErrorKind::NotFound => {}
+                    Err(err) => return Err(err),
+                }
+                let (stream, _) = UnixListener::bind(path)?.accept()?;
+                Connection::Unix(stream)
+            }
+        };
+
+        Ok(Self {
+            writer: connection.try_clone()?,
+            reader: BufReader::new(connection),
+            timeout,
+            pending: Vec::new(),
+            malformed: Vec::new(),
+            timeouts: 0,
+            disconnected: false,
+            #[cfg(unix)]
+            socket_path: match address {
+                BotAddress::Unix(path) => Some(path.clone()),
+                BotAddress::Tcp(_) => None,
+            },
+        })
+    }
+
+    /// States the bot did not answer in time.
+    pub fn timeouts(&self) -> u32 {
+        self.timeouts
+    }
+
+    /// Answers the bot sent since the last call that were not valid JSON replies, they were ignored.
+    pub fn take_malformed_answers(&mut self) -> Vec<serde_json::Error> {
+        std::mem::take(&mut self.malformed)
+    }
+
+    /// Whether the bot closed the connection, its snake then goes straight until the end.
+    pub fn is_disconnected(&self) -> bool {
+        self.disconnected
+    }
+
+    /// Tells the bot how the game ended.
+    pub fn finish(&mut self, world: &SnakeWorld, player: usize) {
+        let snake = &world.snakes()[player];
+        let message = ServerMessage::End {
+            tick: world.tick(),
+            score: snake.score(),
+            won: world.is_won(),
+            death: snake.death().map(|cause| cause.name()),
+            winner: world.winner(),
+        };
+        if self.send(&message).is_err() {
+            self.disconnected = true;
+        }
+    }
+
+    fn send(&mut self, message: &ServerMessage) -> io::Result<()> {
+        let mut line = serde_json::to_vec(message)?;
+        line.push(b'\n');
+        self.writer.write_all(&line)?;
+        self.writer.flush()
+    }
+
+    // The answer to this tick, skipping answers to older ones, a line too long is an error
+    fn receive(&mut self, tick: u32) -> io::Result<Option<Direction>> {
+        let deadline = Instant::now() + self.timeout;
+        loop {
+            let Some(remaining) = deadline
+                .checked_duration_since(Instant::now())
+                .filter(|remaining| !remaining.is_zero())
+            else {
+                return Ok(None);
+            };
+            self.reader.get_ref().set_read_timeout(remaining)?;
+            let limit = MAX_LINE_LENGTH + 1 - self.pending.len();
+            match (&mut self.reader)
+                .take(limit as u64)
+                .read_until(b'\n', &mut self.pending)
+            {
+                Ok(0) => return Err(io::ErrorKind::UnexpectedEof.into()),
+                Ok(_) if self.pending.ends_with(b"\n") => {
+                    let line = std::mem::take(&mut self.pending);
+                    match serde_json::from_slice::<Reply>(&line) {
+                        Ok(reply) if reply.tick.is_none_or(|answered| answered == tick) => {
+                            return Ok(Some(reply.direction))
+                        }
+                        Ok(_) => {}
+                        // A bad line counts as no answer, the next one may be right
+                        Err(_) if line.trim_ascii().is_empty() => {}
+                        Err(err) => self.malformed.push(err),
+                    }
+                }
+                Ok(_) if self.pending.len() > MAX_LINE_LENGTH => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        "answer line too long",
+                    ))
+                }
+                // The connection closed in the middle of a line
+                Ok(_) => return Err(io::ErrorKind::UnexpectedEof.into()),
+                Err(err)
+                    if matches!(
+                        err.kind(),
+                        io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut
+                    ) =>
+                {
+                    return Ok(None)
+                }
+                Err(err) if err.kind() == io::ErrorKind::Interrupted => {}
+                Err(err) => return Err(err),
+            }
+        }
+    }
+}
+
+impl SnakeController for BotController {
+    fn next_direction(&mut self, world: &SnakeWorld, player: usize) -> Direction {
+        let direction = world.snakes()[player].direction();
+        if self.disconnected {
+            return direction;
+        }
+
+        let message = ServerMessage::State {
+            tick: world.tick(),
+            player,
+            mode: world.mode(),
+            arena: world.arena(),
+            obstacles: world.obstacles(),
+            food: world
+                .food()
+                .iter()
+                .map(|(position, kind)| FoodState {
+                    x: position.x,
+                    y: position.y,
+                    kind: *kind,
+                })
+                .collect(),
+            snakes: world
+                .snakes()
+                .iter()
+                .map(|snake| SnakeState {
+                    segments: snake.segments(),
+                    direction: snake.direction(),
+                    score: snake.score(),
+                    alive: snake.death().is_none(),
+                })
+                .collect(),
+        };
+        let reply = self.send(&message).and_then(|_| self.receive(world.tick()));
+        match reply {
+            Ok(Some(reply)) => reply,
+            Ok(None) => {
+                self.timeouts += 1;
+                direction
+            }
+            Err(_) => {
+                self.disconnected = true;
+                direction
+            }
+        }
+    }
+}
+
+#[cfg(unix)]
+impl Drop for BotController {
+    fn drop(&mut self) {
+        if let Some(path) = &self.socket_path {
+            let _ = fs::remove_file(path);
+        }
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use std::{env, thread};
+
+    use rand::{rngs::StdRng, SeedableRng};
+
+    use super::*;
+
+    fn socket_path(name: &str) -> PathBuf {
+        env::temp_dir().join(format!("rusty_snake_{}_{name}.sock", std::process::id()))
+    }
+
+    // Listens on a fresh socket while the bot connects from another thread
+    fn connect(name: &str, timeout: Duration) -> (BotController, UnixStream) {
+        let path = socket_path(name);
+        let bot = thread::spawn({
+            let path = path.clone();
+            move || loop {
+                if let Ok(stream) = UnixStream::connect(&path) {
+                    return stream;
+                }
+                thread::sleep(Duration::from_millis(1));
+            }
+        });
+        let controller = BotController::listen(&BotAddress::Unix(path), timeout).unwrap();
+        (controller, bot.join().unwrap())
+    }
+
+    fn answer(bot: &mut UnixStream, lines: &str) {
+        bot.write_all(lines.as_bytes()).unwrap();
+    }
+
+    #[test]
+    fn the_answer_to_the_state_steers_the_snake() {
+        let (mut controller, mut bot) = connect("answer", DEFAULT_TIMEOUT);
+        let world = SnakeWorld::new(Arena::SMALL);
+        answer(&mut bot, "{\"direction\": \"Left\", \"tick\": 0}\n");
+        assert_eq!(controller.next_direction(&world, 0), Direction::Left);
+
+        let mut state = String::new();
+        BufReader::new(&mut bot).read_line(&mut state).unwrap();
+        let state: serde_json::Value = serde_json::from_str(&state).unwrap();
+        assert_eq!(state["type"], "state");
+        assert_eq!(state["tick"], 0);
+        assert_eq!(controller.timeouts(), 0);
+    }
+
+    #[test]
+    fn answers_to_an_older_tick_are_skipped() {
+        let (mut controller, mut bot) = connect("stale", DEFAULT_TIMEOUT);
+        let mut world = SnakeWorld::new(Arena::SMALL);
+        world.step(world.direction(), &mut StdRng::seed_from_u64(7));
+        answer(
+            &mut bot,
+            "{\"direction\": \"Left\", \"tick\": 0}\n{\"direction\": \"Right\", \"tick\": 1}\n",
+        );
+        assert_eq!(controller.next_direction(&world, 0), Direction::Right);
+    }
+
+    #[test]
+    fn a_late_answer_keeps_the_snake_straight() {
+        let (mut controller, _bot) = connect("timeout", Duration::from_millis(20));
+        let world = SnakeWorld::new(Arena::SMALL);
+        assert_eq!(controller.next_direction(&world, 0), world.direction());
+        assert_eq!(controller.next_direction(&world, 0), world.direction());
+        assert_eq!(controller.timeouts(), 2);
+        assert!(!controller.is_disconnected());
+    }
+
+    #[test]
+    fn malformed_answers_are_reported_and_skipped() {
+        let (mut controller, mut bot) = connect("malformed", DEFAULT_TIMEOUT);
+        let world = SnakeWorld::new(Arena::SMALL);
+        answer(&mut bot, "left please\n\n{\"direction\": \"Left\"}\n");
+        assert_eq!(controller.next_direction(&world, 0), Direction::Left);
+        assert_eq!(controller.take_malformed_answers().len(), 1);
+        assert!(controller.take_malformed_answers().is_empty());
+    }
+
+    #[test]
+    fn a_closed_connection_disconnects_the_bot() {
+        let (mut controller, bot) = connect("closed", DEFAULT_TIMEOUT);
+        drop(bot);
+        let world = SnakeWorld::new(Arena::SMALL);
+        assert_eq!(controller.next_direction(&world, 0), world.direction());
+        assert!(controller.is_disconnected());
+        assert_eq!(controller.timeouts(), 0);
+    }
+
+    #[test]
+    fn an_endless_line_disconnects_the_bot() {
+        let (mut controller, mut bot) = connect("endless", DEFAULT_TIMEOUT);
+        answer(&mut bot, &"x".repeat(MAX_LINE_LENGTH + 1));
+        let world = SnakeWorld::new(Arena::SMALL);
+        assert_eq!(controller.next_direction(&world, 0), world.direction());
+        assert!(controller.is_disconnected());
+    }
+
+    #[test]
+    fn only_a_socket_is_replaced() {
+        let path = socket_path("file");
+        fs::write(&path, "keep me").unwrap();
+        let listened = BotController::listen(&BotAddress::Unix(path.clone()), DEFAULT_TIMEOUT);
+        assert_eq!(
+            listened.err().map(|err| err.kind()),
+            Some(io::ErrorKind::AlreadyExists)
+        );
+        assert_eq!(fs::read_to_string(&path).unwrap(), "keep me");
+        fs::remove_file(&path).unwrap();
+    }
+}
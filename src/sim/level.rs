@@ -0,0 +1,375 @@
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use super::{
+    board::{Arena, Direction, Position},
+    world::SnakeWorld,
+};
+use crate::storage::data_dir;
+
+const EXTENSION: &str = "level";
+
+/// A board to play on: its size, obstacles, where the snake starts and where food appears.
+///
+/// Level files are named after the level, with a few `key value` lines followed by the grid, top row first:
+///
+/// ```text
+/// direction Right
+/// goal length 8
+/// ..........
+/// ..#....#..
+/// ..S..*....
+/// ```
+///
+/// `.` is a free cell, `#` an obstacle, `S` the snake head and `*` a food spot.
+/// The rest of the snake starts right behind the head.
+/// The goal is only used by the campaign, it is `length`, `score` or `survive` followed by a number.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Level {
+    /// File name without its extension, empty for the open board.
+    pub name: String,
+    pub arena: Arena,
+    pub obstacles: Vec<Position>,
+    pub spawn: Position,
+    pub direction: Direction,
+    /// Grow food only ever appears on these cells, any free cell when empty.
+    pub food_spots: Vec<Position>,
+    pub goal: Option<Goal>,
+}
+
+/// What a campaign level asks for before unlocking the next one.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Goal {
+    Length(u32),
+    Score(u32),
+    /// Seconds of game time, slowed down ticks count for more.
+    Survive(u32),
+}
+
+impl Goal {
+    pub fn is_reached(self, world: &SnakeWorld) -> bool {
+        match self {
+            Goal::Length(length) => world.segments().len() as u32 >= length,
+            Goal::Score(score) => world.score() >= score,
+            Goal::Survive(seconds) => world.elapsed() >= seconds as f32,
+        }
+    }
+
+    pub fn describe(self) -> String {
+        match self {
+            Goal::Length(length) => format!("Reach length {length}"),
+            Goal::Score(score) => format!("Score {score} points"),
+            Goal::Survive(seconds) => format!("Survive {seconds} seconds"),
+        }
+    }
+
+    fn encode(self) -> String {
+        match self {
+            Goal::Length(length) => format!("length {length}"),
+            Goal::Score(score) => format!("score {score}"),
+            Goal::Survive(seconds) => format!("survive {seconds}"),
+        }
+    }
+
+    fn parse(value: &str) -> Option<Self> {
+        let (kind, amount) = value.split_once(' ')?;
+        let amount = amount.trim().parse().ok()?;
+        match kind {
+            "length" => Some(Goal::Length(amount)),
+            "score" => Some(Goal::Score(amount)),
+            "survive" => Some(Goal::Survive(amount)),
+            _ => None,
+        }
+    }
+}
+
+impl Level {
+    /// The plain board of the classic game, without any obstacle.
+    pub fn open(arena: Arena) -> Self {
+        Self {
+            name: String::new(),
+            arena,
+            obstacles: Vec::new(),
+            spawn: Position { x: 3, y: 3 },
+            direction: Direction::Up,
+            food_spots: Vec::new(),
+            goal: None,
+        }
+    }
+
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut direction = Direction::Up;
+        let mut goal = None;
+        let mut rows = Vec::new();
+        for line in text.lines().map(str::trim).filter(|line| !line.is_empty()) {
+            match line.split_once(' ') {
+                Some(("direction", value)) => {
+                    direction = parse_direction(value.trim())
+                        .ok_or_else(|| format!("unknown direction {value}"))?
+                }
+                Some(("goal", value)) => {
+                    goal = Some(
+                        Goal::parse(value.trim()).ok_or_else(|| format!("invalid goal {value}"))?,
+                    )
+                }
+                Some((key, _)) => return Err(format!("unknown setting {key}")),
+                None => rows.push(line),
+            }
+        }
+
+        let width = rows.first().map_or(0, |row| row.chars().count());
+        if rows.iter().any(|row| row.chars().count() != width) {
+            return Err("rows have different lengths".to_string());
+        }
+        let arena = Arena::new(width as u32, rows.len() as u32);
+        let sizes = Arena::MIN_SIZE..=Arena::MAX_SIZE;
+        if !sizes.contains(&arena.width) || !sizes.contains(&arena.height) {
+            return Err(format!(
+                "the grid must be between {} and {} cells across",
+                Arena::MIN_SIZE,
+                Arena::MAX_SIZE
+            ));
+        }
+
+        let mut level = Self {
+            direction,
+            goal,
+            ..Self::open(arena)
+        };
+        let mut spawn = None;
+        for (row, line) in rows.iter().enumerate() {
+            // The first row is the top of the board
+            let y = (rows.len() - 1 - row) as i32;
+            for (x, cell) in line.chars().enumerate() {
+                let position = Position { x: x as i32, y };
+                match cell {
+                    '.' => {}
+                    '#' => level.obstacles.push(position),
+                    '*' => level.food_spots.push(position),
+                    'S' if spawn.is_none() => spawn = Some(position),
+                    'S' => return Err("more than one snake".to_string()),
+                    _ => return Err(format!("unknown cell {cell}")),
+                }
+            }
+        }
+        level.spawn = spawn.ok_or("missing snake")?;
+
+        let tail = level.tail();
+        if !arena.contains(tail) || level.obstacles.contains(&tail) {
+            return Err("no room behind the snake".to_string());
+        }
+        // Food would be eaten by the tail before the game even starts
+        if level.food_spots.contains(&tail) {
+            return Err("food behind the snake".to_string());
+        }
+        Ok(level)
+    }
+
+    /// Cell of the second snake segment, behind the head.
+    pub fn tail(&self) -> Position {
+        let mut tail = self.spawn;
+        match self.direction {
+            Direction::Left => tail.x += 1,
+            Direction::Right => tail.x -= 1,
+            Direction::Up => tail.y -= 1,
+            Direction::Down => tail.y += 1,
+        }
+        tail
+    }
+
+    pub fn load(path: &Path) -> io::Result<Self> {
+        let level = Self::parse(&fs::read_to_string(path)?)
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+        let name = path.file_stem().unwrap_or_default().to_string_lossy();
+        Ok(Self {
+            name: name.into_owned(),
+            ..level
+        })
+    }
+
+    /// Loads a bundled level, or one saved from the editor.
+    pub fn load_named(name: &str) -> io::Result<Self> {
+        Self::load(&level_path(name))
+    }
+
+    /// The level file contents, `parse` reads them back.
+    pub fn encode(&self) -> String {
+        let mut text = format!("direction {:?}\n", self.direction);
+        if let Some(goal) = self.goal {
+            text.push_str(&format!("goal {}\n", goal.encode()));
+        }
+        for y in (0..self.arena.height as i32).rev() {
+            for x in 0..self.arena.width as i32 {
+                let position = Position { x, y };
+                text.push(if position == self.spawn {
+                    'S'
+                } else if self.obstacles.contains(&position) {
+                    '#'
+                } else if self.food_spots.contains(&position) {
+                    '*'
+                } else {
+                    '.'
+                });
+            }
+            text.push('\n');
+        }
+        text
+    }
+
+    /// Writes the level in the saved levels folder, under its name.
+    ///
+    /// New levels and edited bundled ones are saved as a copy named after the first free number,
+    /// the bundled files are never written to.
+    pub fn save(&mut self) -> io::Result<PathBuf> {
+        if self.name.is_empty() || is_bundled(&self.name) {
+            let taken = list_levels();
+            self.name = (1..)
+                .map(|number| format!("custom{number}"))
+                .find(|name| !taken.contains(name))
+                .unwrap();
+        }
+        let dir = saved_levels_dir();
+        fs::create_dir_all(&dir)?;
+        let path = dir.join(format!("{}.{EXTENSION}", self.name));
+        fs::write(&path, self.encode())?;
+        Ok(path)
+    }
+}
+
+fn parse_direction(value: &str) -> Option<Direction> {
+    Direction::ALL
+        .into_iter()
+        .find(|direction| format!("{direction:?}").eq_ignore_ascii_case(value))
+}
+
+// Bundled levels come first, a saved level can't take the place of one
+fn level_path(name: &str) -> PathBuf {
+    let file = format!("{name}.{EXTENSION}");
+    let bundled = levels_dir().join(&file);
+    if bundled.exists() {
+        bundled
+    } else {
+        saved_levels_dir().join(file)
+    }
+}
+
+fn is_bundled(name: &str) -> bool {
+    levels_dir().join(format!("{name}.{EXTENSION}")).exists()
+}
+
+/// The bundled `assets/levels` folder, found the same way Bevy finds the assets.
+///
+/// It may be read-only once installed, levels from the editor are saved in `saved_levels_dir`.
+pub fn levels_dir() -> PathBuf {
+    std::env::var_os("BEVY_ASSET_ROOT")
+        .or_else(|| std::env::var_os("CARGO_MANIFEST_DIR"))
+        .map(PathBuf::from)
+        .or_else(|| Some(std::env::current_exe().ok()?.parent()?.to_path_buf()))
+        .unwrap_or_default()
+        .join("assets")
+        .join("levels")
+}
+
+/// Levels saved from the editor, next to the rest of the saved data.
+pub fn saved_levels_dir() -> PathBuf {
+    data_dir().join("levels")
+}
+
+/// Names of the bundled and saved levels, in alphabetical order.
+pub fn list_levels() -> Vec<String> {
+    let mut names = [levels_dir(), saved_levels_dir()]
+        .iter()
+        .filter_map(|dir| fs::read_dir(dir).ok())
+        .flatten()
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.extension().is_some_and(|ext| ext == EXTENSION))
+        .filter_map(|path| Some(path.file_stem()?.to_str()?.to_string()))
+        .collect::<Vec<_>>();
+    names.sort();
+    names.dedup();
+    names
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(x: i32, y: i32) -> Position {
+        Position { x, y }
+    }
+
+    #[test]
+    fn parse_reads_the_grid_top_row_first() {
+        let level = Level::parse(
+            "direction Right\ngoal score 500\n\
+             .....\n\
+             .#..*\n\
+             ..S..\n\
+             .....\n\
+             #....\n",
+        )
+        .unwrap();
+        assert_eq!(level.arena, Arena::new(5, 5));
+        assert_eq!(level.spawn, at(2, 2));
+        assert_eq!(level.tail(), at(1, 2));
+        assert_eq!(level.direction, Direction::Right);
+        assert_eq!(level.goal, Some(Goal::Score(500)));
+        assert_eq!(level.obstacles, [at(1, 3), at(0, 0)]);
+        assert_eq!(level.food_spots, [at(4, 3)]);
+    }
+
+    #[test]
+    fn encode_and_parse_round_trip() {
+        let level = Level {
+            // In the order parse finds them, top row first
+            obstacles: vec![at(6, 4), at(0, 0)],
+            food_spots: vec![at(5, 1)],
+            spawn: at(2, 3),
+            direction: Direction::Down,
+            goal: Some(Goal::Survive(30)),
+            ..Level::open(Arena::new(7, 5))
+        };
+        assert_eq!(Level::parse(&level.encode()), Ok(level));
+    }
+
+    #[test]
+    fn a_level_needs_exactly_one_snake() {
+        assert_eq!(
+            Level::parse(".....\n.....\n.....\n.....\n.....\n"),
+            Err("missing snake".to_string())
+        );
+        assert_eq!(
+            Level::parse(".....\n..S..\n.....\n..S..\n.....\n"),
+            Err("more than one snake".to_string())
+        );
+    }
+
+    #[test]
+    fn cells_outside_the_arena_are_rejected() {
+        // An obstacle sticking out past the other rows
+        assert_eq!(
+            Level::parse(".....\n.....#\n..S..\n.....\n.....\n"),
+            Err("rows have different lengths".to_string())
+        );
+        assert!(Level::parse("....\n....\n.S..\n....\n....\n").is_err());
+    }
+
+    #[test]
+    fn the_tail_needs_a_free_cell() {
+        // Heading up, the tail goes off the bottom edge, into a wall or onto food
+        assert_eq!(
+            Level::parse(".....\n.....\n.....\n.....\n..S..\n"),
+            Err("no room behind the snake".to_string())
+        );
+        assert_eq!(
+            Level::parse(".....\n.....\n..S..\n..#..\n.....\n"),
+            Err("no room behind the snake".to_string())
+        );
+        assert_eq!(
+            Level::parse(".....\n.....\n..S..\n..*..\n.....\n"),
+            Err("food behind the snake".to_string())
+        );
+    }
+}
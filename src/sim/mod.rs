@@ -0,0 +1,6 @@
+pub mod ai;
+pub mod board;
+pub mod bot;
+pub mod level;
+pub mod replay;
+pub mod world;
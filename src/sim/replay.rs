@@ -0,0 +1,396 @@
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use rand::{rngs::StdRng, SeedableRng};
+
+use super::{
+    board::{Arena, Direction, GameMode},
+    level::Level,
+    world::SnakeWorld,
+};
+use crate::storage::data_dir;
+
+// Bumped with every change to the format, replays in another format are refused
+const HEADER: &str = "rusty_snake replay v1";
+const EXTENSION: &str = "replay";
+// Hours of play at the fastest speed, counts adding up to more only come from broken files
+const MAX_TICKS: usize = 1_000_000;
+
+/// Everything needed to play a game again: the seed, the board and the direction given at every tick.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Replay {
+    pub seed: u64,
+    pub mode: GameMode,
+    pub arena: Arena,
+    // Name of the level file, the open arena when unset
+    pub level: Option<String>,
+    /// Board the game was played on, unset on the open arena.
+    pub board: Option<Level>,
+    /// Direction given at every tick, one list per snake.
+    pub inputs: Vec<Vec<Direction>>,
+    /// Final score and tick of the game, so the replay list doesn't play every replay again.
+    pub score: u32,
+    pub ticks: u32,
+}
+
+impl Replay {
+    pub fn new(seed: u64, mode: GameMode, arena: Arena) -> Self {
+        Self {
+            seed,
+            mode,
+            arena,
+            level: None,
+            board: None,
+            inputs: vec![Vec::new()],
+            score: 0,
+            ticks: 0,
+        }
+    }
+
+    /// Records the level the game is played on, the open arena needs nothing.
+    pub fn with_level(self, level: &Level) -> Self {
+        Self {
+            level: (!level.name.is_empty()).then(|| level.name.clone()),
+            board: (*level != Level::open(level.arena)).then(|| level.clone()),
+            ..self
+        }
+    }
+
+    /// A versus game records the inputs of both snakes.
+    pub fn with_players(self, players: usize) -> Self {
+        Self {
+            inputs: vec![Vec::new(); players],
+            ..self
+        }
+    }
+
+    /// Records how the game ended, once it is over.
+    pub fn finish(&mut self, world: &SnakeWorld) {
+        self.score = world.score();
+        self.ticks = world.tick();
+    }
+
+    pub fn players(&self) -> usize {
+        self.inputs.len()
+    }
+
+    /// Number of ticks recorded.
+    pub fn len(&self) -> usize {
+        self.inputs.iter().map(Vec::len).max().unwrap_or(0)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Direction of every snake at this tick, `None` once a snake has no more inputs.
+    pub fn inputs_at(&self, tick: usize) -> Option<Vec<Direction>> {
+        self.inputs
+            .iter()
+            .map(|inputs| inputs.get(tick).copied())
+            .collect()
+    }
+
+    /// Board the game was played on, the embedded one when the level file was edited since.
+    pub fn load_level(&self) -> io::Result<Level> {
+        match (&self.board, &self.level) {
+            (Some(board), _) => Ok(board.clone()),
+            (None, Some(name)) => Level::load_named(name),
+            (None, None) => Ok(Level::open(self.arena)),
+        }
+    }
+
+    /// The game before its first tick, on the level it was played on.
+    pub fn start(&self, level: &Level) -> SnakeWorld {
+        let world = SnakeWorld::from_level(level).with_mode(self.mode);
+        match self.players() {
+            1 => world,
+            _ => world.with_rival(),
+        }
+    }
+
+    /// Runs every recorded tick and returns the final state of the game.
+    ///
+    /// A level that can no longer be read is replaced by the open arena.
+    pub fn simulate(&self) -> SnakeWorld {
+        let level = self
+            .load_level()
+            .unwrap_or_else(|_| Level::open(self.arena));
+        let mut world = self.start(&level);
+        let mut rng = StdRng::seed_from_u64(self.seed);
+        for tick in 0..self.len() {
+            // Every snake has an input at every tick, unless the replay was edited by hand
+            let inputs = self
+                .inputs
+                .iter()
+                .zip(world.snakes())
+                .map(|(inputs, snake)| inputs.get(tick).copied().unwrap_or(snake.direction()))
+                .collect::<Vec<_>>();
+            world.step_all(&inputs, &mut rng);
+        }
+        world
+    }
+
+    /// Inputs are run-length encoded, e.g. `3U2L` for up, up, up, left, left, one line per snake.
+    pub fn encode(&self) -> String {
+        let mut inputs = String::new();
+        for player_inputs in &self.inputs {
+            inputs.push_str("inputs ");
+            for run in player_inputs.chunk_by(|a, b| a == b) {
+                inputs.push_str(&run.len().to_string());
+                inputs.push(direction_to_char(run[0]));
+            }
+            inputs.push('\n');
+        }
+
+        let level = self
+            .level
+            .as_ref()
+            .map_or(String::new(), |name| format!("level {name}\n"));
+        // The number of lines of the level comes first, so they are told apart from the inputs
+        let board = self.board.as_ref().map_or(String::new(), |board| {
+            let board = board.encode();
+            format!("board {}\n{board}", board.lines().count())
+        });
+        format!(
+            "{HEADER}\nseed {}\nmode {}\narena {}x{}\nscore {}\nticks {}\n{level}{board}{inputs}",
+            self.seed,
+            self.mode.name(),
+            self.arena.width,
+            self.arena.height,
+            self.score,
+            self.ticks
+        )
+    }
+
+    pub fn decode(text: &str) -> Option<Self> {
+        let mut lines = text.lines();
+        if lines.next()? != HEADER {
+            return None;
+        }
+        let seed = lines.next()?.strip_prefix("seed ")?.parse().ok()?;
+
+        let mode = GameMode::from_name(lines.next()?.strip_prefix("mode ")?)?;
+        let (width, height) = lines.next()?.strip_prefix("arena ")?.split_once('x')?;
+        let arena = Arena::new(width.parse().ok()?, height.parse().ok()?);
+        let score = lines.next()?.strip_prefix("score ")?.parse().ok()?;
+        let ticks = lines.next()?.strip_prefix("ticks ")?.parse().ok()?;
+        // Games on the open arena have no level nor board lines
+        let mut line = lines.next()?;
+        let mut level = None;
+        if let Some(name) = line.strip_prefix("level ") {
+            level = Some(name.to_string());
+            line = lines.next()?;
+        }
+        let mut board = None;
+        if let Some(count) = line.strip_prefix("board ") {
+            let text = (&mut lines)
+                .take(count.parse().ok()?)
+                .collect::<Vec<_>>()
+                .join("\n");
+            board = Some(Level {
+                name: level.clone().unwrap_or_default(),
+                ..Level::parse(&text).ok()?
+            });
+            line = lines.next()?;
+        }
+
+        let mut inputs = Vec::new();
+        for line in std::iter::once(line).chain(lines.filter(|line| !line.is_empty())) {
+            let mut player_inputs = Vec::new();
+            let mut count = String::new();
+            for c in line.strip_prefix("inputs ")?.chars() {
+                if c.is_ascii_digit() {
+                    count.push(c);
+                } else {
+                    let direction = char_to_direction(c)?;
+                    let count: usize = std::mem::take(&mut count).parse().ok()?;
+                    if player_inputs.len() + count > MAX_TICKS {
+                        return None;
+                    }
+                    player_inputs.extend(std::iter::repeat_n(direction, count));
+                }
+            }
+            inputs.push(player_inputs);
+        }
+
+        Some(Self {
+            seed,
+            mode,
+            arena,
+            level,
+            board,
+            inputs,
+            score,
+            ticks,
+        })
+    }
+
+    pub fn load(path: &Path) -> io::Result<Self> {
+        Self::decode(&fs::read_to_string(path)?)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "invalid replay file"))
+    }
+
+    /// Writes the replay in the replays folder, named after the current time.
+    pub fn save(&self) -> io::Result<PathBuf> {
+        let dir = replays_dir();
+        fs::create_dir_all(&dir)?;
+
+        let millis = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |elapsed| elapsed.as_millis());
+        let path = dir.join(format!("{millis}.{EXTENSION}"));
+        fs::write(&path, self.encode())?;
+        Ok(path)
+    }
+}
+
+pub fn replays_dir() -> PathBuf {
+    data_dir().join("replays")
+}
+
+/// Saved replays, most recent first.
+pub fn list_replays() -> Vec<PathBuf> {
+    let Ok(entries) = fs::read_dir(replays_dir()) else {
+        return Vec::new();
+    };
+
+    let mut paths = entries
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.extension().is_some_and(|ext| ext == EXTENSION))
+        .collect::<Vec<_>>();
+    paths.sort();
+    paths.reverse();
+    paths
+}
+
+fn direction_to_char(direction: Direction) -> char {
+    match direction {
+        Direction::Left => 'L',
+        Direction::Up => 'U',
+        Direction::Right => 'R',
+        Direction::Down => 'D',
+    }
+}
+
+fn char_to_direction(c: char) -> Option<Direction> {
+    match c {
+        'L' => Some(Direction::Left),
+        'U' => Some(Direction::Up),
+        'R' => Some(Direction::Right),
+        'D' => Some(Direction::Down),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Plays a few ticks turning every now and then, recording the inputs like the game does
+    fn record(seed: u64, players: usize) -> (Replay, SnakeWorld) {
+        let mut replay = Replay::new(seed, GameMode::Wrap, Arena::new(12, 9)).with_players(players);
+        let mut world = replay.start(&Level::open(replay.arena));
+        let mut rng = StdRng::seed_from_u64(seed);
+        let turns = [
+            Direction::Up,
+            Direction::Right,
+            Direction::Down,
+            Direction::Left,
+        ];
+        for tick in 0..12 {
+            let inputs = (0..players)
+                .map(|player| turns[(tick / 3 + player) % 4])
+                .collect::<Vec<_>>();
+            world.step_all(&inputs, &mut rng);
+            for (recorded, input) in replay.inputs.iter_mut().zip(inputs) {
+                recorded.push(input);
+            }
+        }
+        replay.finish(&world);
+        (replay, world)
+    }
+
+    #[test]
+    fn encode_and_decode_round_trip() {
+        let (replay, _) = record(42, 2);
+        assert_eq!(replay.encode().lines().nth(6), Some("inputs 3U3R3D3L"));
+        assert_eq!(replay.encode().lines().nth(7), Some("inputs 3R3D3L3U"));
+        assert_eq!(Replay::decode(&replay.encode()), Some(replay));
+    }
+
+    #[test]
+    fn the_level_is_embedded() {
+        let level = Level {
+            name: "tiny".to_string(),
+            ..Level::parse(
+                "direction Right\ngoal length 6\n\
+                 ......\n\
+                 .#..*.\n\
+                 .S....\n\
+                 ...#..\n\
+                 ......\n",
+            )
+            .unwrap()
+        };
+        let replay = Replay::new(3, GameMode::Classic, level.arena).with_level(&level);
+        let decoded = Replay::decode(&replay.encode()).unwrap();
+        assert_eq!(decoded.level.as_deref(), Some("tiny"));
+        assert_eq!(decoded.load_level().unwrap(), level);
+        assert_eq!(decoded, replay);
+    }
+
+    #[test]
+    fn a_decoded_replay_plays_the_same_game() {
+        let (replay, world) = record(9, 1);
+        let decoded = Replay::decode(&replay.encode()).unwrap();
+        assert_eq!(decoded.score, world.score());
+        assert_eq!(decoded.ticks, world.tick());
+        let played = decoded.simulate();
+        assert_eq!(played.segments(), world.segments());
+        assert_eq!(played.food(), world.food());
+        assert_eq!(played.score(), world.score());
+        assert_eq!(played.tick(), world.tick());
+    }
+
+    #[test]
+    fn other_versions_and_broken_replays_are_rejected() {
+        let decode = |header: &str, arena: &str, inputs: &str| {
+            Replay::decode(&format!(
+                "rusty_snake replay {header}\nseed 5\nmode Classic\narena {arena}\nscore 0\nticks 1\ninputs {inputs}\n"
+            ))
+        };
+        assert!(decode("v1", "10x10", "1U").is_some());
+        assert_eq!(decode("v0", "10x10", "1U"), None);
+        assert_eq!(decode("v1", "10x10", "1X"), None);
+        assert_eq!(decode("v1", "10", "1U"), None);
+        assert_eq!(
+            Replay::decode("rusty_snake replay v1\nseed x\nmode Classic\narena 10x10\nscore 0\nticks 1\ninputs 1U\n"),
+            None
+        );
+        assert_eq!(
+            Replay::decode("rusty_snake replay v1\nseed 5\nmode Walls\narena 10x10\nscore 0\nticks 1\ninputs 1U\n"),
+            None
+        );
+        assert_eq!(
+            Replay::decode("rusty_snake replay v1\nseed 5\nmode Classic\narena 10x10\ninputs 1U\n"),
+            None
+        );
+    }
+
+    #[test]
+    fn counts_past_the_tick_limit_are_rejected() {
+        let decode = |inputs: &str| {
+            Replay::decode(&format!(
+                "rusty_snake replay v1\nseed 5\nmode Classic\narena 10x10\nscore 0\nticks 0\ninputs {inputs}\n"
+            ))
+        };
+        assert!(decode(&format!("{MAX_TICKS}U")).is_some());
+        assert_eq!(decode("99999999999999U"), None);
+        // Runs that fit one by one are still counted together
+        assert_eq!(decode(&format!("{MAX_TICKS}U1L")), None);
+    }
+}
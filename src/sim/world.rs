@@ -0,0 +1,725 @@
+use rand::{seq::SliceRandom, Rng};
+
+use super::{
+    board::{Arena, Direction, Food, GameMode, Position},
+    level::Level,
+};
+
+// Food spawns every few seconds of game time, counted in whole milliseconds so a run only depends on its inputs
+const GROW_FOOD_INTERVAL_MS: u32 = 2000;
+const SHRINK_FOOD_INTERVAL_MS: u32 = 8000;
+
+const GROW_FOOD_SCORE: u32 = 100;
+// Filling the whole board is worth this much per cell
+const WIN_SCORE_PER_CELL: u32 = 50;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DeathCause {
+    Wall,
+    Obstacle,
+    Tail,
+    /// Ran into the body of another snake.
+    Snake,
+    /// Two heads met on the same cell.
+    HeadOn,
+}
+
+impl DeathCause {
+    pub fn describe(self) -> &'static str {
+        match self {
+            DeathCause::Wall => "Hit a wall",
+            DeathCause::Obstacle => "Crashed into an obstacle",
+            DeathCause::Tail => "Bit its own tail",
+            DeathCause::Snake => "Ran into the other snake",
+            DeathCause::HeadOn => "Crashed head-on",
+        }
+    }
+
+    /// Short name for machine readable reports.
+    pub fn name(self) -> &'static str {
+        match self {
+            DeathCause::Wall => "wall",
+            DeathCause::Obstacle => "obstacle",
+            DeathCause::Tail => "tail",
+            DeathCause::Snake => "snake",
+            DeathCause::HeadOn => "head_on",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StepOutcome {
+    Moved,
+    Ate(Food),
+    Died(DeathCause),
+    Won,
+}
+
+/// One player's snake and how it is doing.
+#[derive(Clone, Debug)]
+pub struct Snake {
+    // Head first
+    segments: Vec<Position>,
+    direction: Direction,
+    score: u32,
+    grow_eaten: u32,
+    shrink_eaten: u32,
+    death: Option<DeathCause>,
+}
+
+impl Snake {
+    fn new(head: Position, tail: Position, direction: Direction) -> Self {
+        Self {
+            segments: vec![head, tail],
+            direction,
+            score: 0,
+            grow_eaten: 0,
+            shrink_eaten: 0,
+            death: None,
+        }
+    }
+
+    pub fn segments(&self) -> &[Position] {
+        &self.segments
+    }
+
+    pub fn head(&self) -> Position {
+        self.segments[0]
+    }
+
+    pub fn direction(&self) -> Direction {
+        self.direction
+    }
+
+    pub fn score(&self) -> u32 {
+        self.score
+    }
+
+    pub fn food_eaten(&self, food: Food) -> u32 {
+        match food {
+            Food::Grow => self.grow_eaten,
+            Food::Shrink => self.shrink_eaten,
+        }
+    }
+
+    pub fn death(&self) -> Option<DeathCause> {
+        self.death
+    }
+}
+
+/// The snake rules without any rendering: one call to `step` is one movement tick.
+///
+/// Several snakes can share the board, the accessors without a player read the first one.
+#[derive(Clone, Debug)]
+pub struct SnakeWorld {
+    mode: GameMode,
+    // Name of the level, empty on the open board
+    level: String,
+    arena: Arena,
+    obstacles: Vec<Position>,
+    food_spots: Vec<Position>,
+    snakes: Vec<Snake>,
+    food: Vec<(Position, Food)>,
+    tick: u32,
+    // Game time in milliseconds, the sum of every tick duration
+    elapsed_ms: u32,
+    won: bool,
+}
+
+impl Default for SnakeWorld {
+    fn default() -> Self {
+        Self::new(Arena::default())
+    }
+}
+
+impl SnakeWorld {
+    pub fn new(arena: Arena) -> Self {
+        Self::from_level(&Level::open(arena))
+    }
+
+    /// Starts a game on this level, with food already waiting on its food spots.
+    pub fn from_level(level: &Level) -> Self {
+        Self {
+            mode: GameMode::default(),
+            level: level.name.clone(),
+            arena: level.arena,
+            obstacles: level.obstacles.clone(),
+            food_spots: level.food_spots.clone(),
+            snakes: vec![Snake::new(level.spawn, level.tail(), level.direction)],
+            food: level
+                .food_spots
+                .iter()
+                .map(|position| (*position, Food::Grow))
+                .collect(),
+            tick: 0,
+            elapsed_ms: 0,
+            won: false,
+        }
+    }
+
+    pub fn with_mode(self, mode: GameMode) -> Self {
+        Self { mode, ..self }
+    }
+
+    /// Adds a second snake for a versus game, starting opposite the first one.
+    pub fn with_rival(mut self) -> Self {
+        let first = &self.snakes[0];
+        let mirror = |position: Position| Position {
+            x: self.arena.width as i32 - 1 - position.x,
+            y: self.arena.height as i32 - 1 - position.y,
+        };
+        let (mut head, mut tail) = (mirror(first.segments[0]), mirror(first.segments[1]));
+        let mut direction = first.direction.opposite();
+
+        // Levels are not always symmetric, then the rival starts in the first free column pair
+        if self.is_occupied(head) || self.is_occupied(tail) {
+            let free = self.free_cells();
+            if let Some(cell) = free.iter().rev().find(|cell| {
+                free.contains(&Position {
+                    x: cell.x,
+                    y: cell.y + 1,
+                })
+            }) {
+                tail = *cell;
+                head = Position {
+                    x: cell.x,
+                    y: cell.y + 1,
+                };
+                direction = Direction::Up;
+            }
+        }
+        self.food
+            .retain(|(position, _)| *position != head && *position != tail);
+        self.snakes.push(Snake::new(head, tail, direction));
+        self
+    }
+
+    pub fn mode(&self) -> GameMode {
+        self.mode
+    }
+
+    /// Name of the level being played, `None` on the open board.
+    pub fn level(&self) -> Option<&str> {
+        (!self.level.is_empty()).then_some(self.level.as_str())
+    }
+
+    pub fn arena(&self) -> Arena {
+        self.arena
+    }
+
+    pub fn obstacles(&self) -> &[Position] {
+        &self.obstacles
+    }
+
+    pub fn snakes(&self) -> &[Snake] {
+        &self.snakes
+    }
+
+    pub fn segments(&self) -> &[Position] {
+        self.snakes[0].segments()
+    }
+
+    pub fn head(&self) -> Position {
+        self.snakes[0].head()
+    }
+
+    pub fn direction(&self) -> Direction {
+        self.snakes[0].direction()
+    }
+
+    pub fn food(&self) -> &[(Position, Food)] {
+        &self.food
+    }
+
+    pub fn score(&self) -> u32 {
+        self.snakes[0].score()
+    }
+
+    pub fn tick(&self) -> u32 {
+        self.tick
+    }
+
+    /// Game time in seconds.
+    pub fn elapsed(&self) -> f32 {
+        self.elapsed_ms as f32 / 1000.
+    }
+
+    pub fn food_eaten(&self, food: Food) -> u32 {
+        self.snakes[0].food_eaten(food)
+    }
+
+    pub fn death(&self) -> Option<DeathCause> {
+        self.snakes[0].death()
+    }
+
+    pub fn is_won(&self) -> bool {
+        self.won
+    }
+
+    /// A game ends as soon as a snake dies, the others win a versus game.
+    pub fn is_over(&self) -> bool {
+        self.snakes.iter().any(|snake| snake.death.is_some()) || self.won
+    }
+
+    /// Index of the snake that won a finished versus game, `None` for a draw or a game still going.
+    ///
+    /// The last snake alive wins, and when the board fills up the best score does.
+    pub fn winner(&self) -> Option<usize> {
+        if !self.is_over() || self.snakes.len() < 2 {
+            return None;
+        }
+        let alive = (0..self.snakes.len())
+            .filter(|index| self.snakes[*index].death.is_none())
+            .collect::<Vec<_>>();
+        match alive[..] {
+            [winner] => Some(winner),
+            [] => None,
+            _ => {
+                let best = alive.iter().map(|index| self.snakes[*index].score).max()?;
+                let mut best_snakes = alive
+                    .into_iter()
+                    .filter(|index| self.snakes[*index].score == best);
+                let winner = best_snakes.next();
+                best_snakes.next().is_none().then_some(winner).flatten()
+            }
+        }
+    }
+
+    /// Seconds between two movement ticks, the snakes speed up as the best score grows.
+    pub fn tick_duration(&self) -> f32 {
+        self.tick_millis() as f32 / 1000.
+    }
+
+    fn tick_millis(&self) -> u32 {
+        let score = self.snakes.iter().map(Snake::score).max().unwrap_or(0);
+        match score {
+            0..=400 => 200,
+            500..=900 => 180,
+            1000..=1300 => 160,
+            1400..=1600 => 140,
+            1700..=2000 => 120,
+            _ => 100,
+        }
+    }
+
+    /// Fingerprint of the snakes and the food, the same on every machine running the same game.
+    pub fn checksum(&self) -> u64 {
+        // FNV-1a, unlike the std hasher it can't change between builds
+        let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+        let mut add = |value: i32| {
+            for byte in value.to_le_bytes() {
+                hash ^= byte as u64;
+                hash = hash.wrapping_mul(0x0100_0000_01b3);
+            }
+        };
+        for snake in &self.snakes {
+            add(snake.segments.len() as i32);
+            for position in &snake.segments {
+                add(position.x);
+                add(position.y);
+            }
+        }
+        for (position, food) in &self.food {
+            add(position.x);
+            add(position.y);
+            add(*food as i32);
+        }
+        hash
+    }
+
+    /// Cell one step away in that direction, brought back on the board in wrap mode.
+    ///
+    /// It can be off the board in the classic mode.
+    pub fn next_position(&self, mut position: Position, direction: Direction) -> Position {
+        match direction {
+            Direction::Left => position.x -= 1,
+            Direction::Right => position.x += 1,
+            Direction::Up => position.y += 1,
+            Direction::Down => position.y -= 1,
+        }
+        if self.mode == GameMode::Wrap {
+            position = self.arena.wrap(position);
+        }
+        position
+    }
+
+    /// Whether a head moving there dies, not counting heads meeting on the same cell.
+    pub fn is_deadly(&self, position: Position) -> bool {
+        !self.arena.contains(position)
+            || self.obstacles.contains(&position)
+            || self
+                .snakes
+                .iter()
+                .any(|snake| snake.segments.contains(&position))
+    }
+
+    pub fn is_occupied(&self, position: Position) -> bool {
+        self.snakes
+            .iter()
+            .any(|snake| snake.segments.contains(&position))
+            || self.obstacles.contains(&position)
+            || self.food.iter().any(|(pos, _)| *pos == position)
+    }
+
+    /// Moves the first snake, the only one outside of versus games.
+    pub fn step(&mut self, input: Direction, rng: &mut impl Rng) -> StepOutcome {
+        self.step_all(&[input], rng)[0]
+    }
+
+    /// Moves every snake at once, `inputs` holding one direction per snake in order.
+    ///
+    /// Snakes without an input keep their direction.
+    pub fn step_all(&mut self, inputs: &[Direction], rng: &mut impl Rng) -> Vec<StepOutcome> {
+        if self.is_over() {
+            return self
+                .snakes
+                .iter()
+                .map(|snake| self.final_outcome(snake))
+                .collect();
+        }
+
+        for (snake, input) in self.snakes.iter_mut().zip(inputs) {
+            if *input != snake.direction.opposite() {
+                snake.direction = *input;
+            }
+        }
+        let elapsed_before = self.elapsed_ms;
+        self.elapsed_ms += self.tick_millis();
+
+        let heads = self
+            .snakes
+            .iter()
+            .map(|snake| self.next_position(snake.head(), snake.direction))
+            .collect::<Vec<_>>();
+
+        // Every snake moves at the same time, so collisions are checked against where they all end up
+        let deaths = heads
+            .iter()
+            .enumerate()
+            .map(|(index, head)| self.collision(index, *head, &heads))
+            .collect::<Vec<_>>();
+        if deaths.iter().any(Option::is_some) {
+            for (snake, death) in self.snakes.iter_mut().zip(&deaths) {
+                snake.death = *death;
+            }
+            return deaths
+                .iter()
+                .map(|death| death.map_or(StepOutcome::Moved, StepOutcome::Died))
+                .collect();
+        }
+
+        let mut outcomes = Vec::with_capacity(self.snakes.len());
+        for (snake, head) in self.snakes.iter_mut().zip(heads) {
+            snake.segments.insert(0, head);
+            let last_tail = snake.segments.pop().unwrap();
+
+            let mut outcome = StepOutcome::Moved;
+            if let Some(index) = self.food.iter().position(|(pos, _)| *pos == head) {
+                let (_, food) = self.food.remove(index);
+                match food {
+                    Food::Grow => {
+                        snake.segments.push(last_tail);
+                        snake.score += GROW_FOOD_SCORE;
+                        snake.grow_eaten += 1;
+                    }
+                    Food::Shrink => {
+                        snake.shrink_eaten += 1;
+                        if snake.segments.len() > 1 {
+                            snake.segments.pop();
+                        }
+                    }
+                }
+                outcome = StepOutcome::Ate(food);
+            }
+            outcomes.push(outcome);
+        }
+
+        let cells = self.arena.width * self.arena.height - self.obstacles.len() as u32;
+        let length = self
+            .snakes
+            .iter()
+            .map(|snake| snake.segments.len() as u32)
+            .sum::<u32>();
+        if length == cells {
+            self.won = true;
+            for snake in &mut self.snakes {
+                snake.score += WIN_SCORE_PER_CELL * cells;
+            }
+            return vec![StepOutcome::Won; self.snakes.len()];
+        }
+
+        self.tick += 1;
+        // A spawn is due whenever this tick went past one more interval
+        let elapsed = self.elapsed_ms;
+        let due = |interval: u32| elapsed_before / interval != elapsed / interval;
+        if due(GROW_FOOD_INTERVAL_MS) {
+            self.spawn_food(Food::Grow, rng);
+        }
+        if due(SHRINK_FOOD_INTERVAL_MS) {
+            self.spawn_food(Food::Shrink, rng);
+        }
+
+        outcomes
+    }
+
+    // How the snake at `index` dies moving its head there, if it does
+    fn collision(&self, index: usize, head: Position, heads: &[Position]) -> Option<DeathCause> {
+        if !self.arena.contains(head) {
+            return Some(DeathCause::Wall);
+        }
+        if self.obstacles.contains(&head) {
+            return Some(DeathCause::Obstacle);
+        }
+        if heads
+            .iter()
+            .enumerate()
+            .any(|(other, other_head)| other != index && *other_head == head)
+        {
+            return Some(DeathCause::HeadOn);
+        }
+        // Segments are checked where they were before the tick, so a tail doesn't move out of the way in time
+        self.snakes
+            .iter()
+            .enumerate()
+            .find(|(_, snake)| snake.segments.contains(&head))
+            .map(|(other, _)| {
+                if other == index {
+                    DeathCause::Tail
+                } else {
+                    DeathCause::Snake
+                }
+            })
+    }
+
+    fn final_outcome(&self, snake: &Snake) -> StepOutcome {
+        match snake.death {
+            Some(cause) => StepOutcome::Died(cause),
+            None if self.won => StepOutcome::Won,
+            None => StepOutcome::Moved,
+        }
+    }
+
+    fn spawn_food(&mut self, food: Food, rng: &mut impl Rng) {
+        let mut cells = self.free_cells();
+        if food == Food::Grow && !self.food_spots.is_empty() {
+            cells.retain(|position| self.food_spots.contains(position));
+        }
+        // Food has nowhere to go when the board is almost full
+        if let Some(position) = cells.choose(rng) {
+            self.food.push((*position, food));
+        }
+    }
+
+    /// Cells holding neither a snake, an obstacle nor food, row by row.
+    pub fn free_cells(&self) -> Vec<Position> {
+        (0..self.arena.height as i32)
+            .flat_map(|y| (0..self.arena.width as i32).map(move |x| Position { x, y }))
+            .filter(|position| !self.is_occupied(*position))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+impl SnakeWorld {
+    /// Puts the first snake and the food where a test needs them.
+    pub(crate) fn arranged(
+        mut self,
+        segments: &[Position],
+        direction: Direction,
+        food: &[(Position, Food)],
+    ) -> Self {
+        self.snakes[0].segments = segments.to_vec();
+        self.snakes[0].direction = direction;
+        self.food = food.to_vec();
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{rngs::StdRng, SeedableRng};
+
+    use super::*;
+
+    fn rng() -> StdRng {
+        StdRng::seed_from_u64(7)
+    }
+
+    fn at(x: i32, y: i32) -> Position {
+        Position { x, y }
+    }
+
+    // The snake at (3, 3) heading up with its tail at (3, 2)
+    fn world() -> SnakeWorld {
+        SnakeWorld::new(Arena::SMALL)
+    }
+
+    // Goes around a square of four cells, where a short snake never bites itself
+    fn circle(tick: usize) -> Direction {
+        [
+            Direction::Up,
+            Direction::Right,
+            Direction::Down,
+            Direction::Left,
+        ][tick % 4]
+    }
+
+    #[test]
+    fn step_moves_the_whole_snake() {
+        let mut world = world();
+        assert_eq!(world.step(Direction::Right, &mut rng()), StepOutcome::Moved);
+        assert_eq!(world.segments(), [at(4, 3), at(3, 3)]);
+        assert_eq!(world.direction(), Direction::Right);
+        assert_eq!(world.tick(), 1);
+    }
+
+    #[test]
+    fn turning_back_is_ignored() {
+        let mut world = world();
+        world.step(Direction::Down, &mut rng());
+        assert_eq!(world.segments(), [at(3, 4), at(3, 3)]);
+        assert_eq!(world.direction(), Direction::Up);
+    }
+
+    #[test]
+    fn hitting_a_wall_ends_the_game() {
+        let mut world = world();
+        let mut rng = rng();
+        let mut outcome = StepOutcome::Moved;
+        while outcome == StepOutcome::Moved {
+            outcome = world.step(Direction::Up, &mut rng);
+        }
+        assert_eq!(outcome, StepOutcome::Died(DeathCause::Wall));
+        assert_eq!(world.death(), Some(DeathCause::Wall));
+        assert!(world.is_over());
+        // The head stays where it was
+        assert_eq!(world.head(), at(3, 9));
+    }
+
+    #[test]
+    fn wrap_mode_has_no_walls() {
+        let mut world = world().with_mode(GameMode::Wrap);
+        let mut rng = rng();
+        for _ in 0..7 {
+            world.step(Direction::Up, &mut rng);
+        }
+        assert!(!world.is_over());
+        assert_eq!(world.head(), at(3, 0));
+    }
+
+    #[test]
+    fn hitting_an_obstacle_ends_the_game() {
+        let mut world = world();
+        world.obstacles.push(at(3, 4));
+        assert_eq!(
+            world.step(Direction::Up, &mut rng()),
+            StepOutcome::Died(DeathCause::Obstacle)
+        );
+    }
+
+    #[test]
+    fn biting_the_body_ends_the_game() {
+        let segments = [at(3, 3), at(3, 2), at(4, 2), at(4, 3), at(4, 4)];
+        let mut world = world().arranged(&segments, Direction::Up, &[]);
+        assert_eq!(
+            world.step(Direction::Right, &mut rng()),
+            StepOutcome::Died(DeathCause::Tail)
+        );
+    }
+
+    #[test]
+    fn the_tail_does_not_move_out_of_the_way_in_time() {
+        let segments = [at(3, 3), at(3, 2), at(4, 2), at(4, 3)];
+        let mut world = world().arranged(&segments, Direction::Up, &[]);
+        assert_eq!(
+            world.step(Direction::Right, &mut rng()),
+            StepOutcome::Died(DeathCause::Tail)
+        );
+    }
+
+    #[test]
+    fn grow_food_lengthens_the_snake() {
+        let mut world = world().arranged(
+            &[at(3, 3), at(3, 2)],
+            Direction::Up,
+            &[(at(3, 4), Food::Grow)],
+        );
+        assert_eq!(
+            world.step(Direction::Up, &mut rng()),
+            StepOutcome::Ate(Food::Grow)
+        );
+        assert_eq!(world.segments(), [at(3, 4), at(3, 3), at(3, 2)]);
+        assert_eq!(world.score(), GROW_FOOD_SCORE);
+        assert_eq!(world.food_eaten(Food::Grow), 1);
+        assert!(world.food().is_empty());
+    }
+
+    #[test]
+    fn shrink_food_shortens_the_snake() {
+        let segments = [at(3, 3), at(3, 2), at(3, 1)];
+        let mut world = world().arranged(&segments, Direction::Up, &[(at(3, 4), Food::Shrink)]);
+        assert_eq!(
+            world.step(Direction::Up, &mut rng()),
+            StepOutcome::Ate(Food::Shrink)
+        );
+        assert_eq!(world.segments(), [at(3, 4), at(3, 3)]);
+        assert_eq!(world.score(), 0);
+        assert_eq!(world.food_eaten(Food::Shrink), 1);
+    }
+
+    #[test]
+    fn filling_the_board_wins() {
+        let arena = Arena::new(5, 5);
+        // Row by row, turning at the edges, the last cell left for the food
+        let path = (0..5)
+            .flat_map(|y| {
+                let row = (0..5).map(move |x| at(x, y));
+                if y % 2 == 0 {
+                    row.collect::<Vec<_>>()
+                } else {
+                    row.rev().collect()
+                }
+            })
+            .collect::<Vec<_>>();
+        let segments = path[..24].iter().rev().copied().collect::<Vec<_>>();
+        let mut world = SnakeWorld::new(arena).arranged(&segments, Direction::Right, &[]);
+        world.food = vec![(path[24], Food::Grow)];
+
+        assert_eq!(world.step(Direction::Right, &mut rng()), StepOutcome::Won);
+        assert!(world.is_won());
+        assert!(world.is_over());
+        assert_eq!(world.score(), GROW_FOOD_SCORE + WIN_SCORE_PER_CELL * 25);
+    }
+
+    #[test]
+    fn food_spawns_every_two_seconds_of_game_time() {
+        let mut world = world();
+        let mut rng = rng();
+        for tick in 0..9 {
+            world.step(circle(tick), &mut rng);
+        }
+        assert!(world.food().is_empty());
+        world.step(circle(9), &mut rng);
+        assert_eq!(world.elapsed(), 2.);
+        assert_eq!(world.food().len(), 1);
+        assert_eq!(world.food()[0].1, Food::Grow);
+    }
+
+    #[test]
+    fn the_same_seed_and_inputs_play_the_same_game() {
+        let play = |seed| {
+            let mut world = world();
+            let mut rng = StdRng::seed_from_u64(seed);
+            for tick in 0..200 {
+                world.step(circle(tick), &mut rng);
+            }
+            world
+        };
+        let (first, second) = (play(42), play(42));
+        assert_eq!(first.segments(), second.segments());
+        assert_eq!(first.food(), second.food());
+        assert_eq!(first.tick(), second.tick());
+        assert_eq!(first.checksum(), second.checksum());
+        assert_ne!(first.checksum(), play(43).checksum());
+    }
+}
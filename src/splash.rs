@@ -1,6 +1,9 @@
 use bevy::prelude::*;
 
-use crate::utils::{despawn_screen, GameState, TEXT_COLOR};
+use crate::{
+    demo::Demo,
+    utils::{despawn_screen, GameState, TEXT_COLOR},
+};
 
 pub fn splash_plugin(app: &mut App) {
     app.add_systems(OnEnter(GameState::Splash), splash_setup)
@@ -50,12 +53,15 @@ fn splash_setup(mut commands: Commands) {
     commands.insert_resource(SplashTimer(Timer::from_seconds(1., TimerMode::Once)));
 }
 
+// Computer players take over until a key is pressed, see `demo_plugin`
 fn countdown(
+    mut commands: Commands,
     mut game_state: ResMut<NextState<GameState>>,
     time: Res<Time>,
     mut timer: ResMut<SplashTimer>,
 ) {
     if timer.tick(time.delta()).finished() {
-        game_state.set(GameState::Menu);
+        commands.insert_resource(Demo::random());
+        game_state.set(GameState::Game);
     }
 }
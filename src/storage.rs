@@ -0,0 +1,8 @@
+use std::path::PathBuf;
+
+/// Folder holding everything the game saves between runs.
+pub fn data_dir() -> PathBuf {
+    dirs::data_dir()
+        .unwrap_or_else(|| PathBuf::from("."))
+        .join("rusty_snake")
+}
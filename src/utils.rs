@@ -1,12 +1,78 @@
-use bevy::prelude::*;
+use bevy::{prelude::*, window::WindowTheme};
+use serde::{Deserialize, Serialize};
 
-pub const ARENA_WIDTH: u32 = 10;
-pub const ARENA_HEIGHT: u32 = 10;
+pub use crate::sim::board::{Arena, Direction, GameMode, Position};
 
-#[derive(Component, Clone, Copy, PartialEq, Eq)]
-pub struct Position {
-    pub x: i32,
-    pub y: i32,
+/// How many snakes take part in the next games.
+#[derive(
+    Resource, Component, Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize,
+)]
+pub enum Players {
+    #[default]
+    Solo,
+    /// Two snakes on one keyboard, WASD against the arrows, or one gamepad each.
+    Versus,
+    /// Against a snake driven by the computer.
+    Cpu,
+    /// Against a player on another machine, met in the lobby.
+    Online,
+}
+
+impl Players {
+    pub const ALL: [Players; 4] = [
+        Players::Solo,
+        Players::Versus,
+        Players::Cpu,
+        Players::Online,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Players::Solo => "Solo",
+            Players::Versus => "Versus",
+            Players::Cpu => "Vs CPU",
+            Players::Online => "Online",
+        }
+    }
+
+    pub fn count(self) -> usize {
+        match self {
+            Players::Solo => 1,
+            Players::Versus | Players::Cpu | Players::Online => 2,
+        }
+    }
+}
+
+/// Colors of the window title bar and borders.
+#[derive(
+    Resource, Component, Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize,
+)]
+pub enum Theme {
+    /// Whatever the system uses for its other windows.
+    #[default]
+    System,
+    Dark,
+    Light,
+}
+
+impl Theme {
+    pub const ALL: [Theme; 3] = [Theme::System, Theme::Dark, Theme::Light];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Theme::System => "System",
+            Theme::Dark => "Dark",
+            Theme::Light => "Light",
+        }
+    }
+
+    pub fn window_theme(self) -> Option<WindowTheme> {
+        match self {
+            Theme::System => None,
+            Theme::Dark => Some(WindowTheme::Dark),
+            Theme::Light => Some(WindowTheme::Light),
+        }
+    }
 }
 
 #[derive(Component)]
@@ -24,25 +90,6 @@ impl Size {
     }
 }
 
-#[derive(PartialEq, Copy, Clone, Debug)]
-pub enum Direction {
-    Left,
-    Up,
-    Right,
-    Down,
-}
-
-impl Direction {
-    pub fn opposite(self) -> Self {
-        match self {
-            Self::Left => Self::Right,
-            Self::Right => Self::Left,
-            Self::Up => Self::Down,
-            Self::Down => Self::Up,
-        }
-    }
-}
-
 pub const TEXT_COLOR: Color = Color::srgb(0.9, 0.9, 0.9);
 
 #[derive(Clone, Copy, Default, Eq, PartialEq, Debug, Hash, States)]
@@ -51,6 +98,8 @@ pub enum GameState {
     Splash,
     Menu,
     Game,
+    GameOver,
+    Editor,
 }
 
 pub fn despawn_screen<T: Component>(to_despawn: Query<Entity, With<T>>, mut commands: Commands) {
@@ -59,5 +108,22 @@ pub fn despawn_screen<T: Component>(to_despawn: Query<Entity, With<T>>, mut comm
     }
 }
 
+/// Master volume from 0 to 9, scales every sound.
 #[derive(Resource, Debug, Component, PartialEq, Eq, Clone, Copy)]
 pub struct Volume(pub u32);
+
+/// Volume of the sound effects from 0 to 9, on top of the master volume.
+#[derive(Resource, Debug, Component, PartialEq, Eq, Clone, Copy)]
+pub struct SfxVolume(pub u32);
+
+/// Volume of the music from 0 to 9, on top of the master volume.
+#[derive(Resource, Debug, Component, PartialEq, Eq, Clone, Copy)]
+pub struct MusicVolume(pub u32);
+
+/// Level file the next games are played on, the open arena of the settings when unset.
+#[derive(Resource, Debug, Default, PartialEq, Eq, Clone)]
+pub struct SelectedLevel(pub Option<String>);
+
+/// Seed used for the next games, a random one is picked for each game when unset.
+#[derive(Resource, Debug, Default, PartialEq, Eq, Clone, Copy)]
+pub struct Seed(pub Option<u64>);